@@ -0,0 +1,166 @@
+//! Loads a locally downloaded nutrition dataset into `reference_foods`.
+//!
+//! ```text
+//! cargo run --bin import_reference_foods -- usda <FoodData Central CSV directory>
+//! cargo run --bin import_reference_foods -- openfoodfacts <products.csv or products.csv.gz>
+//! ```
+//!
+//! Running it again updates the foods already imported from the same source.
+//!
+//! The USDA files are read whole before importing, see `read_usda` for the memory this takes.
+
+use std::{collections::HashMap, env, fs::File, io::BufReader, path::Path, process};
+
+use backend::util::reference_foods::{
+    read_open_food_facts, read_usda, ReferenceFood, ReferenceSource,
+};
+use flate2::read::GzDecoder;
+use sqlx::{postgres::PgPoolOptions, PgPool};
+
+const BATCH_SIZE: usize = 1000;
+
+fn open(path: &Path) -> Result<Box<dyn std::io::Read>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let reader = BufReader::new(file);
+    if path.extension().is_some_and(|extension| extension == "gz") {
+        Ok(Box::new(GzDecoder::new(reader)))
+    } else {
+        Ok(Box::new(reader))
+    }
+}
+
+async fn save_batch(
+    db: &PgPool,
+    source: ReferenceSource,
+    batch: &[ReferenceFood],
+) -> Result<usize, sqlx::Error> {
+    // A code listed twice would update the same row twice in one statement, the last one wins
+    let mut last = HashMap::new();
+    for (index, food) in batch.iter().enumerate() {
+        last.insert(food.source_id.as_str(), index);
+    }
+    let batch: Vec<&ReferenceFood> = batch
+        .iter()
+        .enumerate()
+        .filter(|(index, food)| last[food.source_id.as_str()] == *index)
+        .map(|(_, food)| food)
+        .collect();
+    let column = |field: fn(&ReferenceFood) -> Option<f64>| -> Vec<Option<f64>> {
+        batch.iter().map(|food| field(food)).collect()
+    };
+
+    sqlx::query!(
+        r#"
+        INSERT INTO reference_foods (source, source_id, name, calories, protein, fat, carbohydrates, fiber, sugar, sodium, brand, barcode)
+        SELECT $1, * FROM UNNEST(
+            $2::text[], $3::text[], $4::float8[], $5::float8[], $6::float8[],
+            $7::float8[], $8::float8[], $9::float8[], $10::float8[], $11::text[], $12::text[]
+        )
+        ON CONFLICT (source, source_id) DO UPDATE SET
+            name = EXCLUDED.name,
+            brand = EXCLUDED.brand,
+            barcode = EXCLUDED.barcode,
+            calories = EXCLUDED.calories,
+            protein = EXCLUDED.protein,
+            fat = EXCLUDED.fat,
+            carbohydrates = EXCLUDED.carbohydrates,
+            fiber = EXCLUDED.fiber,
+            sugar = EXCLUDED.sugar,
+            sodium = EXCLUDED.sodium,
+            import_date = NOW()
+        "#,
+        source.as_str(),
+        &batch.iter().map(|food| food.source_id.clone()).collect::<Vec<_>>(),
+        &batch.iter().map(|food| food.name.clone()).collect::<Vec<_>>(),
+        &column(|food| food.facts.calories) as &[Option<f64>],
+        &column(|food| food.facts.protein) as &[Option<f64>],
+        &column(|food| food.facts.fat) as &[Option<f64>],
+        &column(|food| food.facts.carbohydrates) as &[Option<f64>],
+        &column(|food| food.facts.fiber) as &[Option<f64>],
+        &column(|food| food.facts.sugar) as &[Option<f64>],
+        &column(|food| food.facts.sodium) as &[Option<f64>],
+        &batch.iter().map(|food| food.brand.clone()).collect::<Vec<_>>() as &[Option<String>],
+        &batch.iter().map(|food| food.barcode.clone()).collect::<Vec<_>>() as &[Option<String>]
+    )
+    .execute(db)
+    .await?;
+
+    Ok(batch.len())
+}
+
+async fn import(
+    db: &PgPool,
+    source: ReferenceSource,
+    foods: impl Iterator<Item = Result<ReferenceFood, String>>,
+) -> Result<usize, String> {
+    let mut imported = 0;
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+
+    for food in foods {
+        batch.push(food?);
+        if batch.len() == BATCH_SIZE {
+            imported += save_batch(db, source, &batch)
+                .await
+                .map_err(|e| format!("Failed to save reference foods: {}", e))?;
+            batch.clear();
+            println!("Imported {} foods", imported);
+        }
+    }
+    if !batch.is_empty() {
+        imported += save_batch(db, source, &batch)
+            .await
+            .map_err(|e| format!("Failed to save reference foods: {}", e))?;
+    }
+
+    Ok(imported)
+}
+
+async fn run(source: ReferenceSource, path: &Path) -> Result<usize, String> {
+    let database_url = env::var("DATABASE_URL").map_err(|_| "DATABASE_URL must be set")?;
+    let db = PgPoolOptions::new()
+        .max_connections(1)
+        .connect(&database_url)
+        .await
+        .map_err(|e| format!("Failed to connect to the database: {}", e))?;
+
+    match source {
+        ReferenceSource::Usda => {
+            let branded_food = path.join("branded_food.csv");
+            let foods = read_usda(
+                open(&path.join("food.csv"))?,
+                open(&path.join("food_nutrient.csv"))?,
+                branded_food
+                    .exists()
+                    .then(|| open(&branded_food))
+                    .transpose()?,
+            )?;
+            import(&db, source, foods.into_iter().map(Ok)).await
+        }
+        ReferenceSource::OpenFoodFacts => {
+            import(&db, source, read_open_food_facts(open(path)?)?).await
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    dotenvy::dotenv().ok();
+
+    let args: Vec<String> = env::args().collect();
+    let (Some(source), Some(path)) = (
+        args.get(1)
+            .and_then(|source| ReferenceSource::parse(source)),
+        args.get(2),
+    ) else {
+        eprintln!("Usage: import_reference_foods <usda|openfoodfacts> <path>");
+        process::exit(2);
+    };
+
+    match run(source, Path::new(path)).await {
+        Ok(imported) => println!("Imported {} {} foods", imported, source.as_str()),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use std::env;
 
 use axum::{
+    extract::DefaultBodyLimit,
     http::{
         header::{AUTHORIZATION, CONTENT_TYPE},
         Method,
@@ -18,12 +19,59 @@ use routes::{
         verify::verify_auth,
     },
     calendar::{
-        create_calendar_item, delete_calendar_item, get_calendar_items, update_calendar_item,
+        cook_calendar_item, create_calendar_item, delete_calendar_item, get_calendar_cost,
+        get_calendar_items, update_calendar_item,
     },
     library::{
-        ingredient::{create_ingredient_item, delete_ingredient_item, get_ingredient_items},
-        meal::{create_meal_item, delete_meal_item, get_meal_item, get_meal_items},
+        allergen::{get_allergens, get_allergy_profile, update_allergy_profile},
+        archive::{
+            create_import_job, fail_interrupted_import_jobs, get_import_job, get_import_jobs,
+        },
+        barcode::{create_ingredient_from_barcode, lookup_barcode},
+        category::{
+            create_ingredient_category, delete_ingredient_category, get_ingredient_categories,
+            update_ingredient_category,
+        },
+        diet::{delete_meal_diet_override, get_diets, set_meal_diet_override},
+        equipment::{get_equipment, get_kitchen, update_kitchen},
+        export::{export_meal_item, export_meal_items},
+        import::{import_recipe, save_imported_recipe},
+        ingredient::{
+            create_ingredient_alias, create_ingredient_item, delete_ingredient_alias,
+            delete_ingredient_item, get_ingredient_aliases, get_ingredient_dependents,
+            get_ingredient_items, merge_ingredient_items, parse_ingredient_item_line,
+            update_ingredient_item,
+        },
+        meal::{
+            create_meal_item, delete_meal_item, fork_meal_item, get_cookable_meal_items,
+            get_meal_item, get_meal_item_dependents, get_meal_item_forks, get_meal_items,
+            sync_meal_item_fork, update_meal_item,
+        },
+        nutrition::{get_ingredient_nutrition, update_ingredient_nutrition},
+        price::{create_ingredient_price, delete_ingredient_price, get_ingredient_prices},
+        reference_food::{
+            get_ingredient_reference_food, get_reference_food_suggestions, link_reference_food,
+            search_reference_foods, unlink_reference_food,
+        },
+        season::{
+            delete_ingredient_season, get_ingredient_seasons, get_user_region,
+            update_ingredient_season, update_user_region,
+        },
+        substitution::{
+            create_ingredient_substitution, delete_ingredient_substitution,
+            get_ingredient_substitutions, substitute_meal_ingredient,
+        },
+        tag::{get_tags, get_taxonomy, merge_tags, rename_tag},
     },
+    pantry::{
+        create_pantry_item, delete_pantry_item, get_pantry_items, get_use_soon, update_pantry_item,
+    },
+    shopping_list::{
+        create_shopping_list, create_shopping_list_item, delete_shopping_list,
+        delete_shopping_list_item, export_shopping_list_items, get_shopping_list,
+        get_shopping_lists, update_shopping_list, update_shopping_list_item,
+    },
+    store::{create_store, delete_store, get_stores, update_store},
 };
 use tower_http::cors::CorsLayer;
 
@@ -48,6 +96,7 @@ async fn main() {
         .expose_headers([CONTENT_TYPE, AUTHORIZATION]);
 
     let state = AppState::new().await;
+    fail_interrupted_import_jobs(&state).await;
 
     let auth_router = Router::new()
         .route("/login/google", get(google_login))
@@ -58,15 +107,144 @@ async fn main() {
     let food_router = Router::new()
         .route("/ingredient", get(get_ingredient_items))
         .route("/ingredient", post(create_ingredient_item))
+        .route("/ingredient/category", get(get_ingredient_categories))
+        .route("/ingredient/category", post(create_ingredient_category))
+        .route("/ingredient/category/{id}", put(update_ingredient_category))
+        .route(
+            "/ingredient/category/{id}",
+            delete(delete_ingredient_category),
+        )
+        .route("/ingredient/parse", get(parse_ingredient_item_line))
+        .route("/ingredient/{id}", put(update_ingredient_item))
         .route("/ingredient/{id}", delete(delete_ingredient_item))
+        .route("/ingredient/{id}/alias", get(get_ingredient_aliases))
+        .route("/ingredient/{id}/alias", post(create_ingredient_alias))
+        .route(
+            "/ingredient/{id}/alias/{alias_id}",
+            delete(delete_ingredient_alias),
+        )
+        .route(
+            "/ingredient/{id}/dependents",
+            get(get_ingredient_dependents),
+        )
+        .route("/ingredient/{id}/merge", post(merge_ingredient_items))
+        .route("/ingredient/{id}/nutrition", get(get_ingredient_nutrition))
+        .route(
+            "/ingredient/{id}/nutrition",
+            put(update_ingredient_nutrition),
+        )
+        .route("/ingredient/{id}/price", get(get_ingredient_prices))
+        .route("/ingredient/{id}/price", post(create_ingredient_price))
+        .route("/ingredient/price/{id}", delete(delete_ingredient_price))
+        .route(
+            "/ingredient/{id}/reference-food",
+            get(get_ingredient_reference_food),
+        )
+        .route("/ingredient/{id}/reference-food", put(link_reference_food))
+        .route(
+            "/ingredient/{id}/reference-food",
+            delete(unlink_reference_food),
+        )
+        .route(
+            "/ingredient/{id}/reference-food/suggestions",
+            get(get_reference_food_suggestions),
+        )
+        .route(
+            "/ingredient/{id}/substitute",
+            get(get_ingredient_substitutions),
+        )
+        .route(
+            "/ingredient/{id}/substitute",
+            post(create_ingredient_substitution),
+        )
+        .route(
+            "/ingredient/substitute/{id}",
+            delete(delete_ingredient_substitution),
+        )
+        .route("/ingredient/{id}/season", get(get_ingredient_seasons))
+        .route(
+            "/ingredient/{id}/season/{region}",
+            put(update_ingredient_season),
+        )
+        .route(
+            "/ingredient/{id}/season/{region}",
+            delete(delete_ingredient_season),
+        )
+        .route("/region", get(get_user_region))
+        .route("/region", put(update_user_region))
+        .route("/reference-food", get(search_reference_foods))
+        .route("/allergen", get(get_allergens))
+        .route("/allergy", get(get_allergy_profile))
+        .route("/allergy", put(update_allergy_profile))
+        .route("/diet", get(get_diets))
+        .route("/equipment", get(get_equipment))
+        .route("/kitchen", get(get_kitchen))
+        .route("/kitchen", put(update_kitchen))
+        .route("/taxonomy", get(get_taxonomy))
+        .route("/tag", get(get_tags))
+        .route("/tag/merge", post(merge_tags))
+        .route("/tag/{tag}", put(rename_tag))
+        .route("/meal/{id}/diet/{diet}", put(set_meal_diet_override))
+        .route("/meal/{id}/diet/{diet}", delete(delete_meal_diet_override))
+        .route("/meal/{id}/substitute", post(substitute_meal_ingredient))
+        .route("/barcode/{code}", get(lookup_barcode))
+        .route(
+            "/barcode/{code}/ingredient",
+            post(create_ingredient_from_barcode),
+        )
         .route("/meal", get(get_meal_items))
         .route("/meal", post(create_meal_item))
+        .route("/meal/cookable", get(get_cookable_meal_items))
+        .route("/meal/export", get(export_meal_items))
+        .route("/meal/import", post(import_recipe))
+        .route("/meal/import/save", post(save_imported_recipe))
         .route("/meal/{id}", get(get_meal_item))
+        .route("/meal/{id}", put(update_meal_item))
         .route("/meal/{id}", delete(delete_meal_item))
+        .route("/meal/{id}/dependents", get(get_meal_item_dependents))
+        .route("/meal/{id}/export", get(export_meal_item))
+        .route("/meal/{id}/fork", post(fork_meal_item))
+        .route("/meal/{id}/forks", get(get_meal_item_forks))
+        .route("/meal/{id}/sync", post(sync_meal_item_fork))
+        .route("/import", get(get_import_jobs))
+        .route(
+            "/import",
+            post(create_import_job).layer(DefaultBodyLimit::max(64 * 1024 * 1024)),
+        )
+        .route("/import/{id}", get(get_import_job))
         .route("/calendar", get(get_calendar_items))
         .route("/calendar", post(create_calendar_item))
+        .route("/calendar/cost", get(get_calendar_cost))
         .route("/calendar/{id}", put(update_calendar_item))
         .route("/calendar/{id}", delete(delete_calendar_item))
+        .route("/calendar/{id}/cook", post(cook_calendar_item))
+        .route("/pantry", get(get_pantry_items))
+        .route("/pantry", post(create_pantry_item))
+        .route("/pantry/use-soon", get(get_use_soon))
+        .route("/pantry/{id}", put(update_pantry_item))
+        .route("/pantry/{id}", delete(delete_pantry_item))
+        .route("/shopping-list", get(get_shopping_lists))
+        .route("/shopping-list", post(create_shopping_list))
+        .route("/shopping-list/{id}", get(get_shopping_list))
+        .route("/shopping-list/{id}", put(update_shopping_list))
+        .route("/shopping-list/{id}", delete(delete_shopping_list))
+        .route(
+            "/shopping-list/{id}/export",
+            get(export_shopping_list_items),
+        )
+        .route("/shopping-list/{id}/item", post(create_shopping_list_item))
+        .route(
+            "/shopping-list/{id}/item/{item_id}",
+            put(update_shopping_list_item),
+        )
+        .route(
+            "/shopping-list/{id}/item/{item_id}",
+            delete(delete_shopping_list_item),
+        )
+        .route("/store", get(get_stores))
+        .route("/store", post(create_store))
+        .route("/store/{id}", put(update_store))
+        .route("/store/{id}", delete(delete_store))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             auth_middleware,
@@ -1,11 +1,18 @@
-use axum::extract::Path;
+use axum::extract::{Path, Query};
 use axum::http::StatusCode;
 use axum::{extract::State, Extension, Json};
-use backend::util::{app_state::AppState, user::User};
+use backend::util::{
+    app_state::AppState,
+    costs::{meal_cost, MealCost},
+    units::convert,
+    user::User,
+};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use super::library::price::load_cost_ingredients;
+
 #[derive(Deserialize)]
 pub struct CalendarItemCreationInformation {
     meal_item_id: Uuid,
@@ -39,6 +46,7 @@ pub struct CalendarItemReturn {
     pub meal_item_id: Option<Uuid>,
     pub start_date: DateTime<Utc>,
     pub end_date: DateTime<Utc>,
+    pub cooked_date: Option<DateTime<Utc>>,
 }
 
 pub async fn get_calendar_items(
@@ -47,7 +55,7 @@ pub async fn get_calendar_items(
 ) -> Result<Json<Vec<CalendarItemReturn>>, (StatusCode, String)> {
     let calendar_items = sqlx::query_as!(
         CalendarItemReturn,
-        "SELECT id, meal_item_id, start_date, end_date FROM calendar_items WHERE user_id = $1 OR $1 = ANY(shared_with)",
+        "SELECT id, meal_item_id, start_date, end_date, cooked_date FROM calendar_items WHERE user_id = $1 OR $1 = ANY(shared_with)",
         user.id
     )
     .fetch_all(&state.db)
@@ -104,3 +112,225 @@ pub async fn update_calendar_item(
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update calendar item: {}", e)))?;
     Ok(StatusCode::OK)
 }
+
+#[derive(Serialize)]
+pub struct PantryShortage {
+    pub ingredient_item_id: Uuid,
+    pub name: String,
+    pub quantity: f64,
+    pub unit: Option<String>,
+}
+#[derive(Serialize)]
+pub struct CookCalendarItemResponse {
+    shortages: Vec<PantryShortage>,
+}
+
+/// Leftovers smaller than this are rounding noise and the pantry entry is removed.
+const EMPTY_QUANTITY: f64 = 1e-6;
+
+pub async fn cook_calendar_item(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<CookCalendarItemResponse>, (StatusCode, String)> {
+    let error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to cook calendar item: {}", e),
+        )
+    };
+    let mut tx = state.db.begin().await.map_err(error)?;
+
+    let calendar_item = sqlx::query!(
+        "SELECT meal_item_id, cooked_date FROM calendar_items WHERE id = $1 AND (user_id = $2 OR $2 = ANY(shared_with)) FOR UPDATE",
+        id,
+        user.id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(error)?
+    .ok_or((StatusCode::NOT_FOUND, "Calendar item not found".to_string()))?;
+
+    if calendar_item.cooked_date.is_some() {
+        return Err((
+            StatusCode::CONFLICT,
+            "Calendar item was already cooked".to_string(),
+        ));
+    }
+
+    sqlx::query!(
+        "UPDATE calendar_items SET cooked_date = NOW() WHERE id = $1",
+        id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
+
+    let amounts = sqlx::query!(
+        r#"
+        SELECT a.ingredient_item_id, f.name, a.quantity * e.scale as "quantity!", a.unit
+        FROM expand_meal_item($1) e
+        JOIN meal_item_amounts a ON a.meal_item_id = e.meal_item_id
+        JOIN ingredient_items f ON f.id = a.ingredient_item_id
+        "#,
+        calendar_item.meal_item_id
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(error)?;
+
+    let mut shortages = Vec::new();
+    for amount in amounts {
+        // Whatever expires first is used first
+        let pantry_items = sqlx::query!(
+            r#"
+            SELECT id, quantity, unit FROM pantry_items
+            WHERE user_id = $1 AND ingredient_item_id = $2
+            ORDER BY best_before ASC NULLS LAST, creation_date ASC
+            FOR UPDATE
+            "#,
+            user.id,
+            amount.ingredient_item_id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(error)?;
+
+        let mut remaining = amount.quantity;
+        for pantry_item in pantry_items {
+            if remaining <= EMPTY_QUANTITY {
+                break;
+            }
+            let Some(wanted) = convert(
+                remaining,
+                amount.unit.as_deref(),
+                pantry_item.unit.as_deref(),
+            ) else {
+                continue;
+            };
+            let used = wanted.min(pantry_item.quantity);
+            let left = pantry_item.quantity - used;
+
+            if left <= EMPTY_QUANTITY {
+                sqlx::query!("DELETE FROM pantry_items WHERE id = $1", pantry_item.id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(error)?;
+            } else {
+                sqlx::query!(
+                    "UPDATE pantry_items SET quantity = $1 WHERE id = $2",
+                    left,
+                    pantry_item.id
+                )
+                .execute(&mut *tx)
+                .await
+                .map_err(error)?;
+            }
+
+            remaining -= convert(used, pantry_item.unit.as_deref(), amount.unit.as_deref())
+                .unwrap_or(remaining);
+        }
+
+        if remaining > EMPTY_QUANTITY {
+            shortages.push(PantryShortage {
+                ingredient_item_id: amount.ingredient_item_id,
+                name: amount.name,
+                quantity: remaining,
+                unit: amount.unit,
+            });
+        }
+    }
+
+    tx.commit().await.map_err(error)?;
+    Ok(Json(CookCalendarItemResponse { shortages }))
+}
+
+#[derive(Deserialize)]
+pub struct CalendarCostInformation {
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+}
+#[derive(Serialize)]
+pub struct CalendarItemCost {
+    pub calendar_item_id: Uuid,
+    pub meal_item_id: Uuid,
+    pub name: String,
+    pub start_date: DateTime<Utc>,
+    pub cost: MealCost,
+}
+#[derive(Serialize)]
+pub struct CalendarCostResponse {
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    total: f64,
+    /// False when any planned meal could not be fully priced
+    complete: bool,
+    calendar_items: Vec<CalendarItemCost>,
+}
+
+/// Adds up what the meals planned in a date range cost, each cooked once as written.
+pub async fn get_calendar_cost(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Query(params): Query<CalendarCostInformation>,
+) -> Result<Json<CalendarCostResponse>, (StatusCode, String)> {
+    let error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get calendar cost: {}", e),
+        )
+    };
+
+    let planned = sqlx::query!(
+        r#"
+        SELECT c.id, c.start_date, m.id as meal_item_id, m.name, m.servings
+        FROM calendar_items c
+        JOIN meal_items m ON m.id = c.meal_item_id
+        WHERE (c.user_id = $1 OR $1 = ANY(c.shared_with))
+        AND c.start_date >= $2 AND c.start_date < $3
+        ORDER BY c.start_date ASC
+        "#,
+        user.id,
+        params.start_date,
+        params.end_date
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(error)?;
+
+    // A meal planned several times is only priced once
+    let mut costs: Vec<(Uuid, MealCost)> = Vec::new();
+    let mut calendar_items = Vec::with_capacity(planned.len());
+    for item in planned {
+        let cost = match costs.iter().find(|(id, _)| *id == item.meal_item_id) {
+            Some((_, cost)) => cost.clone(),
+            None => {
+                let ingredients = load_cost_ingredients(&state.db, item.meal_item_id, user.id)
+                    .await
+                    .map_err(error)?;
+                let cost = meal_cost(&ingredients, item.servings);
+                costs.push((item.meal_item_id, cost.clone()));
+                cost
+            }
+        };
+        calendar_items.push(CalendarItemCost {
+            calendar_item_id: item.id,
+            meal_item_id: item.meal_item_id,
+            name: item.name,
+            start_date: item.start_date,
+            cost,
+        });
+    }
+
+    let total = calendar_items
+        .iter()
+        .map(|item| item.cost.total)
+        .sum::<f64>();
+    Ok(Json(CalendarCostResponse {
+        start_date: params.start_date,
+        end_date: params.end_date,
+        total: (total * 100.0).round() / 100.0,
+        complete: calendar_items.iter().all(|item| item.cost.complete),
+        calendar_items,
+    }))
+}
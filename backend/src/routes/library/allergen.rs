@@ -0,0 +1,158 @@
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use backend::util::{
+    allergens::{normalize_allergens, EU_ALLERGENS},
+    app_state::AppState,
+    terms::known_and_custom,
+    user::User,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+#[derive(Serialize)]
+pub struct Allergen {
+    pub name: String,
+    /// One of the 14 allergens EU law requires to be declared
+    pub eu: bool,
+}
+#[derive(Serialize)]
+pub struct AllergensResponse {
+    allergens: Vec<Allergen>,
+}
+
+/// Lists the EU allergens followed by the custom ones ingredients are tagged with.
+pub async fn get_allergens(
+    State(state): State<AppState>,
+) -> Result<Json<AllergensResponse>, (StatusCode, String)> {
+    let custom = sqlx::query_scalar!(
+        r#"
+        SELECT DISTINCT a.allergen as "allergen!"
+        FROM ingredient_items f
+        CROSS JOIN LATERAL unnest(f.allergens) a(allergen)
+        WHERE a.allergen <> ALL($1)
+        ORDER BY 1
+        "#,
+        &EU_ALLERGENS
+            .iter()
+            .map(|allergen| allergen.to_string())
+            .collect::<Vec<_>>()
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get allergens: {}", e),
+        )
+    })?;
+
+    let allergens = known_and_custom(EU_ALLERGENS, custom)
+        .into_iter()
+        .map(|(name, eu)| Allergen { name, eu })
+        .collect();
+
+    Ok(Json(AllergensResponse { allergens }))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AllergyProfile {
+    allergens: Vec<String>,
+}
+
+pub async fn get_allergy_profile(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+) -> Result<Json<AllergyProfile>, (StatusCode, String)> {
+    let allergens = sqlx::query_scalar!(
+        "SELECT allergen FROM user_allergies WHERE user_id = $1 ORDER BY allergen ASC",
+        user.id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get allergy profile: {}", e),
+        )
+    })?;
+
+    Ok(Json(AllergyProfile { allergens }))
+}
+
+/// Replaces the allergens the user has to avoid.
+pub async fn update_allergy_profile(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Json(profile): Json<AllergyProfile>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to update allergy profile: {}", e),
+        )
+    };
+    let mut tx = state.db.begin().await.map_err(error)?;
+
+    sqlx::query!("DELETE FROM user_allergies WHERE user_id = $1", user.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(error)?;
+    sqlx::query!(
+        "INSERT INTO user_allergies (user_id, allergen) SELECT $1, unnest($2::text[])",
+        user.id,
+        &normalize_allergens(&profile.allergens)
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
+
+    tx.commit().await.map_err(error)?;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Serialize)]
+pub struct AllergyWarning {
+    pub allergen: String,
+    pub ingredient_item_id: Uuid,
+    pub name: String,
+}
+
+/// Rolls the ingredient allergens of a meal and its components up to the meal and warns about the ones the user avoids.
+pub async fn load_meal_allergens(
+    db: impl PgExecutor<'_>,
+    meal_item_id: Uuid,
+    user_id: Uuid,
+) -> Result<(Vec<String>, Vec<AllergyWarning>), sqlx::Error> {
+    let found = sqlx::query!(
+        r#"
+        SELECT DISTINCT a.allergen as "allergen!", f.id, f.name,
+        EXISTS (
+            SELECT 1 FROM user_allergies u WHERE u.user_id = $2 AND u.allergen = a.allergen
+        ) as "avoided!"
+        FROM expand_meal_item($1) e
+        JOIN meal_items m ON m.id = e.meal_item_id
+        JOIN ingredient_items f ON f.id = ANY(m.ingredient_items)
+        CROSS JOIN LATERAL unnest(f.allergens) a(allergen)
+        ORDER BY a.allergen ASC, f.name ASC
+        "#,
+        meal_item_id,
+        user_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    let mut allergens: Vec<String> = found.iter().map(|row| row.allergen.clone()).collect();
+    allergens.dedup();
+    let warnings = found
+        .into_iter()
+        .filter(|row| row.avoided)
+        .map(|row| AllergyWarning {
+            allergen: row.allergen,
+            ingredient_item_id: row.id,
+            name: row.name,
+        })
+        .collect();
+
+    Ok((allergens, warnings))
+}
@@ -0,0 +1,284 @@
+use axum::body::Bytes;
+use axum::extract::{Path, Query};
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use backend::util::{
+    app_state::AppState,
+    ingredient_parser::parse_ingredient_line,
+    recipe_archive::{read_archive, ArchiveFormat},
+    schema_org::{parse_servings, SchemaRecipe},
+    user::User,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::diet::refresh_meal_diets;
+use super::import::match_ingredient_line;
+use super::ingredient::find_or_create_ingredient;
+use super::meal::{set_meal_item_amounts, MealItemAmount};
+
+#[derive(Deserialize)]
+pub struct ImportJobCreationInformation {
+    format: ArchiveFormat,
+}
+#[derive(Serialize)]
+pub struct ImportJobCreationResponse {
+    id: Uuid,
+}
+
+pub async fn create_import_job(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Query(params): Query<ImportJobCreationInformation>,
+    archive: Bytes,
+) -> Result<Json<ImportJobCreationResponse>, (StatusCode, String)> {
+    let job = sqlx::query!(
+        "INSERT INTO import_jobs (user_id, format) VALUES ($1, $2) RETURNING id",
+        user.id,
+        params.format.as_str()
+    )
+    .fetch_one(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to create import job: {}", e),
+        )
+    })?;
+
+    tokio::spawn(run_import_job(
+        state,
+        job.id,
+        user.id,
+        params.format,
+        archive,
+    ));
+
+    Ok(Json(ImportJobCreationResponse { id: job.id }))
+}
+
+async fn run_import_job(
+    state: AppState,
+    job_id: Uuid,
+    user_id: Uuid,
+    format: ArchiveFormat,
+    archive: Bytes,
+) {
+    let entries = match read_archive(format, &archive) {
+        Ok(entries) => entries,
+        Err(e) => {
+            finish_import_job(&state, job_id, "failed", Some(e)).await;
+            return;
+        }
+    };
+
+    for entry in entries {
+        let (status, meal_item_id, message) = match entry.recipe {
+            Ok(recipe) => match import_recipe_entry(&state, user_id, recipe).await {
+                Ok(result) => result,
+                Err(e) => (
+                    "failed",
+                    None,
+                    Some(format!("Failed to save recipe: {}", e)),
+                ),
+            },
+            Err(e) => ("failed", None, Some(e)),
+        };
+
+        if let Err(e) = sqlx::query!(
+            "INSERT INTO import_job_items (job_id, name, status, meal_item_id, message) VALUES ($1, $2, $3, $4, $5)",
+            job_id,
+            entry.name.chars().take(255).collect::<String>(),
+            status,
+            meal_item_id,
+            message
+        )
+        .execute(&state.db)
+        .await
+        {
+            eprintln!("Failed to record import job item: {}", e);
+        }
+    }
+
+    finish_import_job(&state, job_id, "finished", None).await;
+}
+
+/// Jobs run inside the server process, so any still running at startup were cut off by a restart.
+pub async fn fail_interrupted_import_jobs(state: &AppState) {
+    if let Err(e) = sqlx::query!(
+        r#"
+        UPDATE import_jobs SET status = 'failed', error = 'The import was interrupted by a server restart', finish_date = NOW()
+        WHERE status = 'running'
+        "#
+    )
+    .execute(&state.db)
+    .await
+    {
+        eprintln!("Failed to fail interrupted import jobs: {}", e);
+    }
+}
+
+async fn finish_import_job(state: &AppState, job_id: Uuid, status: &str, error: Option<String>) {
+    if let Err(e) = sqlx::query!(
+        "UPDATE import_jobs SET status = $1, error = $2, finish_date = NOW() WHERE id = $3",
+        status,
+        error,
+        job_id
+    )
+    .execute(&state.db)
+    .await
+    {
+        eprintln!("Failed to finish import job: {}", e);
+    }
+}
+
+async fn import_recipe_entry(
+    state: &AppState,
+    user_id: Uuid,
+    recipe: SchemaRecipe,
+) -> Result<(&'static str, Option<Uuid>, Option<String>), sqlx::Error> {
+    let duplicate = sqlx::query!(
+        "SELECT id FROM meal_items WHERE creator_id = $1 AND lower(name) = lower($2) LIMIT 1",
+        user_id,
+        recipe.name
+    )
+    .fetch_optional(&state.db)
+    .await?;
+
+    if let Some(duplicate) = duplicate {
+        return Ok((
+            "duplicate",
+            Some(duplicate.id),
+            Some("A meal with this name already exists".to_string()),
+        ));
+    }
+
+    let mut tx = state.db.begin().await?;
+
+    let mut ingredient_ids = Vec::with_capacity(recipe.ingredients.len());
+    let mut amounts = Vec::new();
+    for line in &recipe.ingredients {
+        let parsed = parse_ingredient_line(line);
+        let id = match match_ingredient_line(&mut *tx, line).await? {
+            Some((id, _name)) => id,
+            None => {
+                if parsed.ingredient.is_empty() {
+                    continue;
+                }
+                find_or_create_ingredient(&mut tx, &parsed.ingredient, user_id).await?
+            }
+        };
+        if !ingredient_ids.contains(&id) {
+            ingredient_ids.push(id);
+            if let Some(quantity) = parsed.quantity {
+                amounts.push(MealItemAmount {
+                    ingredient_id: id,
+                    quantity,
+                    unit: parsed.unit,
+                });
+            }
+        }
+    }
+
+    let meal = sqlx::query!(
+        r#"
+        INSERT INTO meal_items (name, ingredient_items, instructions, creator_id, servings, prep_time, cook_time, total_time)
+        VALUES ($1, $2, $3, $4, COALESCE($5, 1), $6, $7, $8)
+        RETURNING id
+        "#,
+        recipe.name,
+        &ingredient_ids,
+        recipe.instructions.join("\n"),
+        user_id,
+        recipe.recipe_yield.as_deref().and_then(parse_servings),
+        recipe.prep_time,
+        recipe.cook_time,
+        recipe.total_time
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    set_meal_item_amounts(&mut tx, meal.id, &amounts).await?;
+    refresh_meal_diets(&mut tx, &[meal.id]).await?;
+
+    tx.commit().await?;
+
+    Ok(("imported", Some(meal.id), None))
+}
+
+#[derive(Serialize)]
+pub struct ImportJobItem {
+    pub name: String,
+    pub status: String,
+    pub meal_item_id: Option<Uuid>,
+    pub message: Option<String>,
+}
+#[derive(Serialize)]
+pub struct ImportJob {
+    pub id: Uuid,
+    pub format: String,
+    pub status: String,
+    pub error: Option<String>,
+    pub creation_date: DateTime<Utc>,
+    pub finish_date: Option<DateTime<Utc>>,
+}
+#[derive(Serialize)]
+pub struct ImportJobResponse {
+    job: ImportJob,
+    imported: i64,
+    duplicates: i64,
+    failed: i64,
+    items: Vec<ImportJobItem>,
+}
+
+pub async fn get_import_jobs(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<ImportJob>>, (StatusCode, String)> {
+    let jobs = sqlx::query_as!(
+        ImportJob,
+        "SELECT id, format, status, error, creation_date, finish_date FROM import_jobs WHERE user_id = $1 ORDER BY creation_date DESC",
+        user.id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get import jobs: {}", e)))?;
+
+    Ok(Json(jobs))
+}
+
+pub async fn get_import_job(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ImportJobResponse>, (StatusCode, String)> {
+    let job = sqlx::query_as!(
+        ImportJob,
+        "SELECT id, format, status, error, creation_date, finish_date FROM import_jobs WHERE id = $1 AND user_id = $2",
+        id,
+        user.id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get import job: {}", e)))?
+    .ok_or((StatusCode::NOT_FOUND, "Import job not found".to_string()))?;
+
+    let items = sqlx::query_as!(
+        ImportJobItem,
+        "SELECT name, status, meal_item_id, message FROM import_job_items WHERE job_id = $1 ORDER BY creation_date ASC",
+        id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get import job items: {}", e)))?;
+
+    let count = |status: &str| items.iter().filter(|item| item.status == status).count() as i64;
+
+    Ok(Json(ImportJobResponse {
+        imported: count("imported"),
+        duplicates: count("duplicate"),
+        failed: count("failed"),
+        job,
+        items,
+    }))
+}
@@ -0,0 +1,184 @@
+use axum::extract::Path;
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use backend::util::{
+    app_state::AppState, barcodes::normalize_barcode, ingredient_names::ingredient_key,
+    store_sections::normalize_section, user::User,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use super::ingredient::{duplicate_error, find_ingredient_by_name};
+use super::reference_food::ReferenceFood;
+
+/// Normalizes a barcode from a request, rejecting codes that fail their check digit.
+pub fn parse_barcode(code: &str) -> Result<String, (StatusCode, String)> {
+    normalize_barcode(code).ok_or((
+        StatusCode::BAD_REQUEST,
+        format!("\"{}\" is not a valid UPC or EAN barcode", code),
+    ))
+}
+
+async fn find_product(
+    db: impl PgExecutor<'_>,
+    barcode: &str,
+) -> Result<Option<ReferenceFood>, (StatusCode, String)> {
+    sqlx::query_as!(
+        ReferenceFood,
+        r#"
+        SELECT id, source, source_id, name, brand, barcode, calories, protein, fat, carbohydrates,
+        fiber, sugar, sodium, NULL::real as "score?"
+        FROM reference_foods
+        WHERE barcode = $1
+        ORDER BY import_date DESC
+        LIMIT 1
+        "#,
+        barcode
+    )
+    .fetch_optional(db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to look up barcode: {}", e),
+        )
+    })
+}
+
+#[derive(Serialize)]
+pub struct BarcodeIngredient {
+    pub id: Uuid,
+    pub name: String,
+}
+#[derive(Serialize)]
+pub struct BarcodeLookupResponse {
+    barcode: String,
+    /// The ingredient scanned before or linked to the product
+    ingredient: Option<BarcodeIngredient>,
+    /// The product from the imported product database
+    product: Option<ReferenceFood>,
+}
+
+pub async fn lookup_barcode(
+    State(state): State<AppState>,
+    Path(code): Path<String>,
+) -> Result<Json<BarcodeLookupResponse>, (StatusCode, String)> {
+    let barcode = parse_barcode(&code)?;
+    let product = find_product(&state.db, &barcode).await?;
+
+    // An ingredient carrying the barcode wins over one that is only linked to the product
+    let ingredient = sqlx::query_as!(
+        BarcodeIngredient,
+        r#"
+        SELECT id, name
+        FROM ingredient_items
+        WHERE barcode = $1 OR reference_food_id = $2
+        ORDER BY (barcode = $1) DESC NULLS LAST, creation_date ASC
+        LIMIT 1
+        "#,
+        barcode,
+        product.as_ref().map(|product| product.id)
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to look up barcode: {}", e),
+        )
+    })?;
+
+    if ingredient.is_none() && product.is_none() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "No product found for this barcode".to_string(),
+        ));
+    }
+
+    Ok(Json(BarcodeLookupResponse {
+        barcode,
+        ingredient,
+        product,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct BarcodeIngredientCreationInformation {
+    /// Defaults to the product name
+    name: Option<String>,
+    category_id: Option<Uuid>,
+    store_section: Option<String>,
+}
+#[derive(Serialize)]
+pub struct BarcodeIngredientCreationResponse {
+    id: Uuid,
+}
+
+/// Creates an ingredient from a scanned product, linked to it and with its nutrition facts.
+pub async fn create_ingredient_from_barcode(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(code): Path<String>,
+    Json(ingredient_item): Json<BarcodeIngredientCreationInformation>,
+) -> Result<Json<BarcodeIngredientCreationResponse>, (StatusCode, String)> {
+    let barcode = parse_barcode(&code)?;
+    let product = find_product(&state.db, &barcode).await?.ok_or((
+        StatusCode::NOT_FOUND,
+        "No product found for this barcode".to_string(),
+    ))?;
+
+    let name = ingredient_item
+        .name
+        .as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .unwrap_or(&product.name)
+        .to_string();
+    let existing = find_ingredient_by_name(&state.db, &name)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to create ingredient item: {}", e),
+            )
+        })?;
+    if let Some((id, existing_name)) = existing {
+        return Err(duplicate_error(&name, id, &existing_name));
+    }
+
+    let created = sqlx::query!(
+        r#"
+        INSERT INTO ingredient_items (name, name_key, creator_id, category_id, store_section, barcode, reference_food_id,
+            calories, protein, fat, carbohydrates, fiber, sugar, sodium)
+        SELECT $1, $2, $3, $4, $5, $6, r.id, r.calories, r.protein, r.fat, r.carbohydrates, r.fiber, r.sugar, r.sodium
+        FROM reference_foods r
+        WHERE r.id = $7
+        ON CONFLICT (barcode) DO NOTHING
+        RETURNING id
+        "#,
+        name,
+        ingredient_key(&name),
+        user.id,
+        ingredient_item.category_id,
+        ingredient_item
+            .store_section
+            .as_deref()
+            .map(normalize_section),
+        barcode,
+        product.id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to create ingredient item: {}", e),
+        )
+    })?
+    .ok_or((
+        StatusCode::CONFLICT,
+        "An ingredient with this barcode already exists".to_string(),
+    ))?;
+
+    Ok(Json(BarcodeIngredientCreationResponse { id: created.id }))
+}
@@ -0,0 +1,218 @@
+use axum::extract::Path;
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use backend::util::{app_state::AppState, store_sections::normalize_section, user::User};
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+/// Rejects a parent that sits below the category itself, which would close a loop.
+async fn check_category_parent(
+    db: impl PgExecutor<'_>,
+    id: Uuid,
+    parent_id: Uuid,
+) -> Result<(), (StatusCode, String)> {
+    let cycle = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM ingredient_category_ancestors
+            WHERE category_id = $1 AND ancestor_id = $2
+        ) as "cycle!"
+        "#,
+        parent_id,
+        id
+    )
+    .fetch_one(db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to check category parent: {}", e),
+        )
+    })?
+    .cycle;
+
+    if cycle {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "A category cannot be placed under itself or one of its children".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct IngredientCategoryCreationInformation {
+    name: String,
+    parent_id: Option<Uuid>,
+    store_section: Option<String>,
+}
+#[derive(Serialize)]
+pub struct IngredientCategoryCreationResponse {
+    id: Uuid,
+}
+
+pub async fn create_ingredient_category(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Json(category): Json<IngredientCategoryCreationInformation>,
+) -> Result<Json<IngredientCategoryCreationResponse>, (StatusCode, String)> {
+    let created = sqlx::query!(
+        "INSERT INTO ingredient_categories (name, parent_id, store_section, creator_id) VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING RETURNING id",
+        category.name.trim(),
+        category.parent_id,
+        category.store_section.as_deref().map(normalize_section),
+        user.id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| match e.as_database_error() {
+        Some(error) if error.is_foreign_key_violation() => (
+            StatusCode::BAD_REQUEST,
+            "Parent category not found".to_string(),
+        ),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to create ingredient category: {}", e),
+        ),
+    })?
+    .ok_or((StatusCode::CONFLICT, "An ingredient category with this name already exists".to_string()))?;
+
+    Ok(Json(IngredientCategoryCreationResponse { id: created.id }))
+}
+
+#[derive(Serialize)]
+pub struct IngredientCategory {
+    pub id: Uuid,
+    pub name: String,
+    pub parent_id: Option<Uuid>,
+    pub store_section: Option<String>,
+    pub creator_id: Uuid,
+    /// Ingredients in this category or any category below it
+    pub ingredient_count: i64,
+}
+#[derive(Serialize)]
+pub struct IngredientCategoriesResponse {
+    categories: Vec<IngredientCategory>,
+}
+
+pub async fn get_ingredient_categories(
+    State(state): State<AppState>,
+) -> Result<Json<IngredientCategoriesResponse>, (StatusCode, String)> {
+    let categories = sqlx::query_as!(
+        IngredientCategory,
+        r#"
+        SELECT c.id, c.name, c.parent_id, c.store_section, c.creator_id,
+        (
+            SELECT count(*)
+            FROM ingredient_items f
+            JOIN ingredient_category_ancestors a ON a.category_id = f.category_id
+            WHERE a.ancestor_id = c.id
+        ) as "ingredient_count!"
+        FROM ingredient_categories c
+        ORDER BY c.name ASC
+        "#
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get ingredient categories: {}", e),
+        )
+    })?;
+
+    Ok(Json(IngredientCategoriesResponse { categories }))
+}
+
+#[derive(Deserialize)]
+pub struct IngredientCategoryUpdateInformation {
+    name: Option<String>,
+    parent_id: Option<Uuid>,
+    /// Moves the category back to the top level, `parent_id` is ignored when set
+    #[serde(default)]
+    remove_parent: bool,
+    store_section: Option<String>,
+}
+
+pub async fn update_ingredient_category(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(category): Json<IngredientCategoryUpdateInformation>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    if let Some(parent_id) = category.parent_id.filter(|_| !category.remove_parent) {
+        check_category_parent(&state.db, id, parent_id).await?;
+    }
+
+    let result = sqlx::query!(
+        r#"
+        UPDATE ingredient_categories SET
+            name = COALESCE($1, name),
+            parent_id = CASE WHEN $2 THEN NULL ELSE COALESCE($3, parent_id) END,
+            store_section = COALESCE($4, store_section)
+        WHERE id = $5 AND creator_id = $6
+        "#,
+        category.name.as_deref().map(str::trim),
+        category.remove_parent,
+        category.parent_id,
+        category.store_section.as_deref().map(normalize_section),
+        id,
+        user.id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|e| match e.as_database_error() {
+        Some(error) if error.is_unique_violation() => (
+            StatusCode::CONFLICT,
+            "An ingredient category with this name already exists".to_string(),
+        ),
+        Some(error) if error.is_foreign_key_violation() => (
+            StatusCode::BAD_REQUEST,
+            "Parent category not found".to_string(),
+        ),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to update ingredient category: {}", e),
+        ),
+    })?;
+
+    if result.rows_affected() == 0 {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Ingredient category not found".to_string(),
+        ));
+    }
+
+    Ok(StatusCode::OK)
+}
+
+pub async fn delete_ingredient_category(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    // Child categories move up to the top level and ingredients lose their category
+    let result = sqlx::query!(
+        "DELETE FROM ingredient_categories WHERE id = $1 AND creator_id = $2",
+        id,
+        user.id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to delete ingredient category: {}", e),
+        )
+    })?;
+
+    if result.rows_affected() == 0 {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Ingredient category not found".to_string(),
+        ));
+    }
+
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,269 @@
+use axum::extract::Path;
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use backend::util::{
+    app_state::AppState,
+    diets::{
+        classify_meal, ingredient_properties, Diet, DietIngredient, DietLabel, DietOverride,
+        DietRule, DIET_RULES,
+    },
+    nutrition::meal_nutrition,
+    user::User,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use super::nutrition::load_nutrition_ingredients;
+
+/// Works out every diet label of a meal along with the reason for it.
+pub async fn load_meal_diets(
+    conn: &mut PgConnection,
+    meal_item_id: Uuid,
+) -> Result<Vec<DietLabel>, sqlx::Error> {
+    let servings = sqlx::query_scalar!(
+        "SELECT servings FROM meal_items WHERE id = $1",
+        meal_item_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let ingredients = sqlx::query!(
+        r#"
+        SELECT DISTINCT f.id, f.name, f.properties, f.allergens
+        FROM expand_meal_item($1) e
+        JOIN meal_items m ON m.id = e.meal_item_id
+        JOIN ingredient_items f ON f.id = ANY(m.ingredient_items)
+        ORDER BY f.name ASC
+        "#,
+        meal_item_id
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|ingredient| DietIngredient {
+        classified: ingredient.properties.is_some(),
+        properties: ingredient_properties(
+            ingredient.properties.as_deref().unwrap_or_default(),
+            &ingredient.allergens,
+        ),
+        name: ingredient.name,
+    })
+    .collect::<Vec<_>>();
+
+    let overrides = sqlx::query!(
+        "SELECT diet, applies, note FROM meal_item_diet_overrides WHERE meal_item_id = $1",
+        meal_item_id
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .filter_map(|row| {
+        Some(DietOverride {
+            diet: Diet::parse(&row.diet)?,
+            applies: row.applies,
+            note: row.note,
+        })
+    })
+    .collect::<Vec<_>>();
+
+    let nutrition = meal_nutrition(
+        &load_nutrition_ingredients(&mut *conn, meal_item_id).await?,
+        servings,
+    );
+
+    Ok(classify_meal(&ingredients, &nutrition, &overrides))
+}
+
+/// Stores the diets that apply on each meal, and the ingredients, equipment and time it
+/// takes with its sub-recipes, which is what `get_meal_items` filters by.
+/// A sub-recipe without a time leaves the meal without one.
+/// Called whenever a meal's ingredients or the ingredients themselves change, meals using
+/// the changed ones as components are refreshed too.
+pub async fn refresh_meal_diets(
+    conn: &mut PgConnection,
+    meal_item_ids: &[Uuid],
+) -> Result<(), sqlx::Error> {
+    let meal_item_ids = sqlx::query_scalar!(
+        r#"
+        WITH RECURSIVE affected (id) AS (
+            SELECT unnest($1::uuid[])
+            UNION
+            SELECT c.meal_item_id FROM meal_item_components c
+            JOIN affected a ON c.component_id = a.id
+        )
+        SELECT id as "id!" FROM affected
+        "#,
+        meal_item_ids
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE meal_items m SET
+            expanded_ingredient_items = ARRAY(
+                SELECT DISTINCT i FROM expand_meal_item(m.id) c
+                JOIN meal_items y ON y.id = c.meal_item_id
+                CROSS JOIN LATERAL unnest(y.ingredient_items) i
+                ORDER BY i
+            ),
+            expanded_equipment = ARRAY(
+                SELECT DISTINCT e FROM expand_meal_item(m.id) c
+                JOIN meal_items y ON y.id = c.meal_item_id
+                CROSS JOIN LATERAL unnest(y.equipment) e
+                ORDER BY e
+            ),
+            expanded_ready_time = (
+                SELECT CASE WHEN bool_and(y.ready_time IS NOT NULL) THEN sum(y.ready_time) END
+                FROM expand_meal_item(m.id) c
+                JOIN meal_items y ON y.id = c.meal_item_id
+            )
+        WHERE m.id = ANY($1)
+        "#,
+        &meal_item_ids
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    for meal_item_id in &meal_item_ids {
+        let diets: Vec<String> = load_meal_diets(&mut *conn, *meal_item_id)
+            .await?
+            .into_iter()
+            .filter(|label| label.applies)
+            .map(|label| label.diet.as_str().to_string())
+            .collect();
+
+        sqlx::query!(
+            "UPDATE meal_items SET diets = $1 WHERE id = $2",
+            &diets,
+            meal_item_id
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Refreshes the diets of every meal using one of the ingredients.
+pub async fn refresh_ingredient_meal_diets(
+    conn: &mut PgConnection,
+    ingredient_item_ids: &[Uuid],
+) -> Result<(), sqlx::Error> {
+    let meal_item_ids = sqlx::query_scalar!(
+        "SELECT id FROM meal_items WHERE ingredient_items && $1",
+        ingredient_item_ids
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    refresh_meal_diets(conn, &meal_item_ids).await
+}
+
+#[derive(Serialize)]
+pub struct DietsResponse {
+    diets: &'static [DietRule],
+}
+
+pub async fn get_diets() -> Json<DietsResponse> {
+    Json(DietsResponse { diets: DIET_RULES })
+}
+
+fn parse_diet(diet: &str) -> Result<Diet, (StatusCode, String)> {
+    Diet::parse(diet).ok_or((
+        StatusCode::BAD_REQUEST,
+        format!("Unknown diet \"{}\"", diet),
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct DietOverrideInformation {
+    applies: bool,
+    /// Shown with the label, such as "uses vegan butter"
+    note: Option<String>,
+}
+
+/// Lets the creator set a diet label regardless of what the ingredients suggest.
+pub async fn set_meal_diet_override(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path((id, diet)): Path<(Uuid, String)>,
+    Json(diet_override): Json<DietOverrideInformation>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let diet = parse_diet(&diet)?;
+    let error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to set meal diet: {}", e),
+        )
+    };
+    let mut tx = state.db.begin().await.map_err(error)?;
+
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO meal_item_diet_overrides (meal_item_id, diet, applies, note)
+        SELECT id, $3, $4, $5 FROM meal_items WHERE id = $1 AND creator_id = $2
+        ON CONFLICT (meal_item_id, diet) DO UPDATE
+        SET applies = EXCLUDED.applies, note = EXCLUDED.note
+        "#,
+        id,
+        user.id,
+        diet.as_str(),
+        diet_override.applies,
+        diet_override.note
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Meal item not found".to_string()));
+    }
+
+    refresh_meal_diets(&mut tx, &[id]).await.map_err(error)?;
+
+    tx.commit().await.map_err(error)?;
+    Ok(StatusCode::OK)
+}
+
+/// Goes back to the derived label.
+pub async fn delete_meal_diet_override(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path((id, diet)): Path<(Uuid, String)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let diet = parse_diet(&diet)?;
+    let error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to reset meal diet: {}", e),
+        )
+    };
+    let mut tx = state.db.begin().await.map_err(error)?;
+
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM meal_item_diet_overrides o
+        USING meal_items m
+        WHERE o.meal_item_id = m.id AND m.id = $1 AND m.creator_id = $2 AND o.diet = $3
+        "#,
+        id,
+        user.id,
+        diet.as_str()
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
+
+    if result.rows_affected() == 0 {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Meal diet override not found".to_string(),
+        ));
+    }
+
+    refresh_meal_diets(&mut tx, &[id]).await.map_err(error)?;
+
+    tx.commit().await.map_err(error)?;
+    Ok(StatusCode::OK)
+}
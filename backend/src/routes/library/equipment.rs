@@ -0,0 +1,109 @@
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use backend::util::{
+    app_state::AppState,
+    equipment::{normalize_equipment, COMMON_EQUIPMENT},
+    terms::known_and_custom,
+    user::User,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize)]
+pub struct Equipment {
+    pub name: String,
+    pub common: bool,
+}
+#[derive(Serialize)]
+pub struct EquipmentResponse {
+    equipment: Vec<Equipment>,
+}
+
+/// Lists the common equipment followed by the custom equipment meals ask for.
+pub async fn get_equipment(
+    State(state): State<AppState>,
+) -> Result<Json<EquipmentResponse>, (StatusCode, String)> {
+    let custom = sqlx::query_scalar!(
+        r#"
+        SELECT DISTINCT e.equipment as "equipment!"
+        FROM meal_items m
+        CROSS JOIN LATERAL unnest(m.equipment) e(equipment)
+        WHERE e.equipment <> ALL($1)
+        ORDER BY 1
+        "#,
+        &COMMON_EQUIPMENT
+            .iter()
+            .map(|equipment| equipment.to_string())
+            .collect::<Vec<_>>()
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get equipment: {}", e),
+        )
+    })?;
+
+    let equipment = known_and_custom(COMMON_EQUIPMENT, custom)
+        .into_iter()
+        .map(|(name, common)| Equipment { name, common })
+        .collect();
+
+    Ok(Json(EquipmentResponse { equipment }))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Kitchen {
+    equipment: Vec<String>,
+}
+
+pub async fn get_kitchen(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+) -> Result<Json<Kitchen>, (StatusCode, String)> {
+    let equipment = sqlx::query_scalar!(
+        "SELECT equipment FROM user_equipment WHERE user_id = $1 ORDER BY equipment ASC",
+        user.id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get kitchen: {}", e),
+        )
+    })?;
+
+    Ok(Json(Kitchen { equipment }))
+}
+
+/// Replaces the equipment the user has at home.
+pub async fn update_kitchen(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Json(kitchen): Json<Kitchen>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to update kitchen: {}", e),
+        )
+    };
+    let mut tx = state.db.begin().await.map_err(error)?;
+
+    sqlx::query!("DELETE FROM user_equipment WHERE user_id = $1", user.id)
+        .execute(&mut *tx)
+        .await
+        .map_err(error)?;
+    sqlx::query!(
+        "INSERT INTO user_equipment (user_id, equipment) SELECT $1, unnest($2::text[])",
+        user.id,
+        &normalize_equipment(&kitchen.equipment)
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
+
+    tx.commit().await.map_err(error)?;
+
+    Ok(StatusCode::OK)
+}
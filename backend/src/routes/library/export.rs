@@ -0,0 +1,174 @@
+use axum::extract::Path;
+use axum::http::header::{HeaderName, CONTENT_DISPOSITION, CONTENT_TYPE};
+use axum::{extract::State, http::StatusCode, Extension};
+use axum_extra::extract::Query;
+use backend::util::{
+    app_state::AppState,
+    ingredient_parser::format_ingredient_line,
+    recipe_export::{export_recipes, ExportFormat},
+    schema_org::SchemaRecipe,
+    user::User,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+type ExportResponse = ([(HeaderName, String); 2], Vec<u8>);
+
+#[derive(Deserialize)]
+struct ExportIngredient {
+    name: String,
+    quantity: Option<f64>,
+    unit: Option<String>,
+}
+
+async fn load_export_recipes(
+    state: &AppState,
+    ids: &[Uuid],
+) -> Result<Vec<SchemaRecipe>, (StatusCode, String)> {
+    let meals = sqlx::query!(
+        r#"
+        SELECT m.name, m.instructions, m.servings, m.prep_time, m.cook_time, m.ready_time,
+        u.first_name || ' ' || u.last_name as "author!",
+        (
+            SELECT COALESCE(
+                json_agg(json_build_object(
+                    'name', f.name, 'quantity', a.quantity, 'unit', a.unit
+                ) ORDER BY i.position),
+                '[]'::json
+            )
+            FROM unnest(m.ingredient_items) WITH ORDINALITY AS i(id, position)
+            JOIN ingredient_items f ON f.id = i.id
+            LEFT JOIN meal_item_amounts a ON a.meal_item_id = m.id AND a.ingredient_item_id = f.id
+        ) as "ingredients!"
+        FROM meal_items m
+        JOIN users u ON u.id = m.creator_id
+        WHERE m.id = ANY($1)
+        ORDER BY array_position($1, m.id)
+        "#,
+        ids
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get meal items: {}", e),
+        )
+    })?;
+
+    Ok(meals
+        .into_iter()
+        .map(|meal| SchemaRecipe {
+            name: meal.name,
+            author: Some(meal.author.trim().to_string()).filter(|author| !author.is_empty()),
+            ingredients: serde_json::from_value::<Vec<ExportIngredient>>(meal.ingredients)
+                .unwrap()
+                .into_iter()
+                .map(|ingredient| {
+                    format_ingredient_line(
+                        ingredient.quantity,
+                        ingredient.unit.as_deref(),
+                        &ingredient.name,
+                    )
+                })
+                .collect(),
+            instructions: meal
+                .instructions
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect(),
+            recipe_yield: Some(meal.servings.to_string()),
+            prep_time: meal.prep_time,
+            cook_time: meal.cook_time,
+            total_time: meal.ready_time,
+        })
+        .collect())
+}
+
+fn export_response(
+    format: ExportFormat,
+    title: &str,
+    recipes: &[SchemaRecipe],
+) -> Result<ExportResponse, (StatusCode, String)> {
+    let body = export_recipes(format, title, recipes)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+    let file_name: String = title
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+
+    Ok((
+        [
+            (CONTENT_TYPE, format.content_type().to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"{}.{}\"",
+                    file_name,
+                    format.extension()
+                ),
+            ),
+        ],
+        body,
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct ExportMealItemInformation {
+    format: ExportFormat,
+}
+
+pub async fn export_meal_item(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<ExportMealItemInformation>,
+) -> Result<ExportResponse, (StatusCode, String)> {
+    let recipes = load_export_recipes(&state, &[id]).await?;
+    let recipe = recipes
+        .first()
+        .ok_or((StatusCode::NOT_FOUND, "Meal not found".to_string()))?;
+
+    export_response(params.format, &recipe.name, &recipes)
+}
+
+#[derive(Deserialize)]
+pub struct ExportMealItemsInformation {
+    format: ExportFormat,
+    title: Option<String>,
+    #[serde(default)]
+    ids: Vec<Uuid>,
+}
+
+pub async fn export_meal_items(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Query(params): Query<ExportMealItemsInformation>,
+) -> Result<ExportResponse, (StatusCode, String)> {
+    // Without a selection the whole library the user created is exported
+    let ids = if params.ids.is_empty() {
+        sqlx::query_scalar!(
+            "SELECT id FROM meal_items WHERE creator_id = $1 ORDER BY name ASC",
+            user.id
+        )
+        .fetch_all(&state.db)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to get meal items: {}", e),
+            )
+        })?
+    } else {
+        params.ids
+    };
+
+    let recipes = load_export_recipes(&state, &ids).await?;
+    let title = params
+        .title
+        .unwrap_or_else(|| format!("{}'s Cookbook", user.first_name));
+
+    export_response(params.format, &title, &recipes)
+}
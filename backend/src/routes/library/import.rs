@@ -0,0 +1,215 @@
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use backend::util::{
+    app_state::AppState,
+    equipment::{normalize_equipment, Difficulty},
+    ingredient_parser::parse_ingredient_line,
+    schema_org::{parse_recipe_document, parse_servings},
+    tags::{normalize_tags, Course, Cuisine},
+    user::User,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use super::diet::refresh_meal_diets;
+use super::ingredient::{find_ingredient_matches, find_or_create_ingredient};
+use super::meal::{set_meal_item_amounts, MealItemAmount};
+
+#[derive(Serialize)]
+pub struct RecipeDraftIngredient {
+    line: String,
+    ingredient_id: Option<Uuid>,
+    name: String,
+    quantity: Option<f64>,
+    unit: Option<String>,
+}
+#[derive(Serialize)]
+pub struct RecipeDraft {
+    name: String,
+    ingredients: Vec<RecipeDraftIngredient>,
+    instructions: String,
+    recipe_yield: Option<String>,
+    servings: Option<i32>,
+    prep_time: Option<i32>,
+    cook_time: Option<i32>,
+    total_time: Option<i32>,
+}
+
+/// Fuzzy matches below this similarity are treated as a new ingredient.
+const MATCH_THRESHOLD: f32 = 0.5;
+
+pub async fn match_ingredient_line(
+    db: impl PgExecutor<'_>,
+    line: &str,
+) -> Result<Option<(Uuid, String)>, sqlx::Error> {
+    let parsed = parse_ingredient_line(line);
+    if parsed.ingredient.is_empty() {
+        return Ok(None);
+    }
+
+    let best = find_ingredient_matches(db, &parsed.ingredient, 1)
+        .await?
+        .into_iter()
+        .next()
+        .filter(|best| best.score >= MATCH_THRESHOLD);
+
+    Ok(best.map(|best| (best.id, best.name)))
+}
+
+pub async fn import_recipe(
+    State(state): State<AppState>,
+    document: String,
+) -> Result<Json<RecipeDraft>, (StatusCode, String)> {
+    let recipe =
+        parse_recipe_document(&document).map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e))?;
+
+    let mut ingredients = Vec::with_capacity(recipe.ingredients.len());
+    for line in recipe.ingredients {
+        let existing = match_ingredient_line(&state.db, &line).await.map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to match ingredient: {}", e),
+            )
+        })?;
+
+        let parsed = parse_ingredient_line(&line);
+        let (ingredient_id, name) = match existing {
+            Some((id, name)) => (Some(id), name),
+            None => (None, parsed.ingredient),
+        };
+        ingredients.push(RecipeDraftIngredient {
+            line,
+            ingredient_id,
+            name,
+            quantity: parsed.quantity,
+            unit: parsed.unit,
+        });
+    }
+
+    Ok(Json(RecipeDraft {
+        name: recipe.name,
+        ingredients,
+        instructions: recipe.instructions.join("\n"),
+        servings: recipe.recipe_yield.as_deref().and_then(parse_servings),
+        recipe_yield: recipe.recipe_yield,
+        prep_time: recipe.prep_time,
+        cook_time: recipe.cook_time,
+        total_time: recipe.total_time,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct ImportIngredientInformation {
+    ingredient_id: Option<Uuid>,
+    name: String,
+    quantity: Option<f64>,
+    unit: Option<String>,
+}
+#[derive(Deserialize)]
+pub struct ImportRecipeInformation {
+    name: String,
+    ingredients: Vec<ImportIngredientInformation>,
+    instructions: String,
+    servings: Option<i32>,
+    prep_time: Option<i32>,
+    cook_time: Option<i32>,
+    total_time: Option<i32>,
+    difficulty: Option<Difficulty>,
+    #[serde(default)]
+    equipment: Vec<String>,
+    cuisine: Option<Cuisine>,
+    course: Option<Course>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+#[derive(Serialize)]
+pub struct ImportRecipeResponse {
+    id: Uuid,
+}
+
+pub async fn save_imported_recipe(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Json(recipe): Json<ImportRecipeInformation>,
+) -> Result<Json<ImportRecipeResponse>, (StatusCode, String)> {
+    let mut tx = state.db.begin().await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to start transaction: {}", e),
+        )
+    })?;
+
+    let mut ingredient_ids = Vec::with_capacity(recipe.ingredients.len());
+    let mut amounts = Vec::new();
+    for ingredient in recipe.ingredients {
+        let id = match ingredient.ingredient_id {
+            Some(id) => id,
+            None => find_or_create_ingredient(&mut tx, &ingredient.name, user.id)
+                .await
+                .map_err(|e| {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        format!("Failed to create ingredient item: {}", e),
+                    )
+                })?,
+        };
+        if !ingredient_ids.contains(&id) {
+            ingredient_ids.push(id);
+            if let Some(quantity) = ingredient.quantity {
+                amounts.push(MealItemAmount {
+                    ingredient_id: id,
+                    quantity,
+                    unit: ingredient.unit,
+                });
+            }
+        }
+    }
+
+    let meal = sqlx::query!(
+        r#"
+        INSERT INTO meal_items (name, ingredient_items, instructions, creator_id, servings, prep_time, cook_time, total_time, difficulty, equipment, cuisine, course, tags)
+        VALUES ($1, $2, $3, $4, COALESCE($5, 1), $6, $7, $8, $9, $10, $11, $12, $13)
+        RETURNING id
+        "#,
+        recipe.name,
+        &ingredient_ids,
+        recipe.instructions,
+        user.id,
+        recipe.servings,
+        recipe.prep_time,
+        recipe.cook_time,
+        recipe.total_time,
+        recipe.difficulty.map(|difficulty| difficulty.as_str()),
+        &normalize_equipment(&recipe.equipment),
+        recipe.cuisine.map(|cuisine| cuisine.as_str()),
+        recipe.course.map(|course| course.as_str()),
+        &normalize_tags(&recipe.tags)
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create meal item: {}", e)))?;
+
+    set_meal_item_amounts(&mut tx, meal.id, &amounts)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to save ingredient amounts: {}", e),
+            )
+        })?;
+    refresh_meal_diets(&mut tx, &[meal.id]).await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to classify meal diets: {}", e),
+        )
+    })?;
+
+    tx.commit().await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to save imported recipe: {}", e),
+        )
+    })?;
+
+    Ok(Json(ImportRecipeResponse { id: meal.id }))
+}
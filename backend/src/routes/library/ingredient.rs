@@ -1,12 +1,114 @@
 use axum::extract::{Path, Query};
 use axum::{extract::State, http::StatusCode, Extension, Json};
-use backend::util::{app_state::AppState, user::User};
+use backend::util::{
+    allergens::normalize_allergens,
+    app_state::AppState,
+    deletion::{dependents_message, DeleteMode},
+    diets::IngredientProperty,
+    ingredient_names::ingredient_key,
+    ingredient_parser::{parse_ingredient_line, ParsedIngredient},
+    nutrition::convert_amount,
+    search::{highlight, SearchHighlight},
+    seasons::{current_month, region_fallbacks},
+    store_sections::normalize_section,
+    user::User,
+};
 use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgExecutor};
 use uuid::Uuid;
 
+use super::barcode::parse_barcode;
+use super::diet::{refresh_ingredient_meal_diets, refresh_meal_diets};
+use super::season::load_user_region;
+
+/// Finds the ingredient a name refers to, ignoring case and plurals and following aliases.
+pub async fn find_ingredient_by_name(
+    db: impl PgExecutor<'_>,
+    name: &str,
+) -> Result<Option<(Uuid, String)>, sqlx::Error> {
+    let found = sqlx::query!(
+        r#"
+        SELECT f.id, f.name
+        FROM ingredient_items f
+        LEFT JOIN ingredient_aliases a ON a.ingredient_item_id = f.id
+        WHERE f.name_key = $1 OR a.name_key = $1
+        ORDER BY (f.name_key = $1) DESC, f.creation_date ASC
+        LIMIT 1
+        "#,
+        ingredient_key(name)
+    )
+    .fetch_optional(db)
+    .await?;
+
+    Ok(found.map(|found| (found.id, found.name)))
+}
+
+/// Returns the existing ingredient for a name, creating it when there is none.
+pub async fn find_or_create_ingredient(
+    conn: &mut PgConnection,
+    name: &str,
+    creator_id: Uuid,
+) -> Result<Uuid, sqlx::Error> {
+    if let Some((id, _name)) = find_ingredient_by_name(&mut *conn, name).await? {
+        return Ok(id);
+    }
+
+    let created = sqlx::query!(
+        "INSERT INTO ingredient_items (name, name_key, creator_id) VALUES ($1, $2, $3) RETURNING id",
+        name,
+        ingredient_key(name),
+        creator_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(created.id)
+}
+
+pub fn duplicate_error(name: &str, existing_id: Uuid, existing_name: &str) -> (StatusCode, String) {
+    (
+        StatusCode::CONFLICT,
+        format!(
+            "\"{}\" already exists as \"{}\" ({})",
+            name, existing_name, existing_id
+        ),
+    )
+}
+
+fn property_names(properties: &[IngredientProperty]) -> Vec<String> {
+    let mut names: Vec<String> = properties
+        .iter()
+        .map(|property| property.as_str().to_string())
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Maps a barcode already taken by another ingredient to a conflict.
+fn barcode_error(context: &'static str) -> impl Fn(sqlx::Error) -> (StatusCode, String) {
+    move |e| match e.as_database_error() {
+        Some(error) if error.is_unique_violation() => (
+            StatusCode::CONFLICT,
+            "Another ingredient already has this barcode".to_string(),
+        ),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("{}: {}", context, e),
+        ),
+    }
+}
+
 #[derive(Deserialize)]
 pub struct IngredientItemCreationInformation {
     name: String,
+    category_id: Option<Uuid>,
+    store_section: Option<String>,
+    barcode: Option<String>,
+    #[serde(default)]
+    allergens: Vec<String>,
+    /// Left out when nobody has checked yet, which keeps meals from claiming diets they may not fit
+    properties: Option<Vec<IngredientProperty>>,
 }
 
 pub async fn create_ingredient_item(
@@ -14,19 +116,41 @@ pub async fn create_ingredient_item(
     State(state): State<AppState>,
     Json(ingredient_item): Json<IngredientItemCreationInformation>,
 ) -> Result<StatusCode, (StatusCode, String)> {
+    let barcode = ingredient_item
+        .barcode
+        .as_deref()
+        .map(parse_barcode)
+        .transpose()?;
+    let existing = find_ingredient_by_name(&state.db, &ingredient_item.name)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to create ingredient item: {}", e),
+            )
+        })?;
+    if let Some((id, name)) = existing {
+        return Err(duplicate_error(&ingredient_item.name, id, &name));
+    }
+
+    let properties = ingredient_item.properties.as_deref().map(property_names);
     sqlx::query!(
-        "INSERT INTO ingredient_items (name, creator_id) VALUES ($1, $2)",
+        "INSERT INTO ingredient_items (name, name_key, creator_id, category_id, store_section, barcode, allergens, properties) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
         ingredient_item.name,
-        user.id
+        ingredient_key(&ingredient_item.name),
+        user.id,
+        ingredient_item.category_id,
+        ingredient_item
+            .store_section
+            .as_deref()
+            .map(normalize_section),
+        barcode,
+        &normalize_allergens(&ingredient_item.allergens),
+        properties.as_deref()
     )
     .execute(&state.db)
     .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to create ingredient item: {}", e),
-        )
-    })?;
+    .map_err(barcode_error("Failed to create ingredient item"))?;
     Ok(StatusCode::OK)
 }
 
@@ -35,6 +159,16 @@ pub struct IngredientItem {
     pub id: Uuid,
     pub name: String,
     pub creator_id: Uuid,
+    pub category_id: Option<Uuid>,
+    pub store_section: Option<String>,
+    pub barcode: Option<String>,
+    pub allergens: Vec<String>,
+    /// None until the ingredient has been classified
+    pub properties: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rank: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlight: Option<SearchHighlight>,
 }
 #[derive(Serialize)]
 pub struct IngredientItemsResponse {
@@ -46,6 +180,9 @@ pub struct GetIngredientItemsInformation {
     end: Option<i64>,
     search: Option<String>,
     mine: Option<bool>,
+    category: Option<Uuid>,
+    /// Only ingredients in season this month in the user's region
+    in_season: Option<bool>,
 }
 
 pub async fn get_ingredient_items(
@@ -55,67 +192,526 @@ pub async fn get_ingredient_items(
 ) -> Result<Json<IngredientItemsResponse>, (StatusCode, String)> {
     let start = params.start.unwrap_or(0);
     let end = params.end.unwrap_or(10);
-
-    let query: Vec<IngredientItem> = if let Some(search) = params.search {
-        if params.mine.unwrap_or(false) {
-            sqlx::query_as!(
-                IngredientItem,
-                "SELECT id, name, creator_id FROM ingredient_items WHERE name ILIKE $1 AND creator_id = $2 ORDER BY name ASC OFFSET $3 LIMIT $4",
-                format!("%{}%", search),
-                user.id,
-                start,
-                end
+    let search = params.search.filter(|search| !search.trim().is_empty());
+    let in_season = params.in_season.unwrap_or(false);
+    let regions = if in_season {
+        let region = load_user_region(&state.db, user.id).await.map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to get ingredient items: {}", e),
             )
-            .fetch_all(&state.db)
-            .await
-        } else {
-            sqlx::query_as!(
-                IngredientItem,
-                "SELECT id, name, creator_id FROM ingredient_items WHERE name ILIKE $1 ORDER BY name ASC OFFSET $2 LIMIT $3",
-                format!("%{}%", search),
-                start,
-                end
-            )
-            .fetch_all(&state.db)
-            .await
-        }
+        })?;
+        region_fallbacks(&region)
     } else {
-        if params.mine.unwrap_or(false) {
-            sqlx::query_as!(
-                IngredientItem,
-                "SELECT id, name, creator_id FROM ingredient_items WHERE creator_id = $1 ORDER BY name ASC OFFSET $2 LIMIT $3",
-                user.id,
-                start,
-                end
+        Vec::new()
+    };
+
+    // Without a search the list is alphabetical, with one it is ranked by similarity.
+    // Searching for a category such as "cheese" also finds the ingredients filed below it.
+    let query = sqlx::query!(
+        r#"
+        SELECT f.id, f.name, f.creator_id,
+        CASE WHEN $1::text IS NULL THEN NULL ELSE GREATEST(
+            similarity(lower(f.name), lower($1)) + (f.name ILIKE $1 || '%')::int,
+            COALESCE((
+                SELECT max(similarity(lower(c.name), lower($1)))
+                FROM ingredient_category_ancestors a
+                JOIN ingredient_categories c ON c.id = a.ancestor_id
+                WHERE a.category_id = f.category_id
+            ), 0)
+        ) END as "rank?: f64",
+        f.category_id, f.store_section, f.barcode, f.allergens, f.properties
+        FROM ingredient_items f
+        WHERE ($1::text IS NULL
+            OR lower(f.name) % lower($1)
+            OR f.name ILIKE '%' || $1 || '%'
+            OR EXISTS (
+                SELECT 1 FROM ingredient_category_ancestors a
+                JOIN ingredient_categories c ON c.id = a.ancestor_id
+                WHERE a.category_id = f.category_id
+                AND (lower(c.name) % lower($1) OR c.name ILIKE '%' || $1 || '%')
             )
-            .fetch_all(&state.db)
+        )
+        AND (NOT $2 OR f.creator_id = $3)
+        AND ($6::uuid IS NULL OR EXISTS (
+            SELECT 1 FROM ingredient_category_ancestors a
+            WHERE a.category_id = f.category_id AND a.ancestor_id = $6
+        ))
+        AND (NOT $7 OR $8 = ANY((
+            SELECT s.months FROM ingredient_seasons s
+            WHERE s.ingredient_item_id = f.id AND s.region = ANY($9)
+            ORDER BY array_position($9, s.region::text)
+            LIMIT 1
+        )::int[]))
+        ORDER BY 4 DESC NULLS LAST, f.name ASC
+        OFFSET $4 LIMIT $5
+        "#,
+        search,
+        params.mine.unwrap_or(false),
+        user.id,
+        start,
+        end,
+        params.category,
+        in_season,
+        current_month(),
+        &regions
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get ingredient items: {}", e),
+        )
+    })?;
+
+    let ingredient_items = query
+        .into_iter()
+        .map(|item| IngredientItem {
+            highlight: search
+                .as_deref()
+                .and_then(|search| highlight("name", &item.name, search)),
+            id: item.id,
+            name: item.name,
+            creator_id: item.creator_id,
+            category_id: item.category_id,
+            store_section: item.store_section,
+            barcode: item.barcode,
+            allergens: item.allergens,
+            properties: item.properties,
+            rank: item.rank,
+        })
+        .collect();
+
+    Ok(Json(IngredientItemsResponse { ingredient_items }))
+}
+
+#[derive(Deserialize)]
+pub struct IngredientItemUpdateInformation {
+    name: Option<String>,
+    category_id: Option<Uuid>,
+    store_section: Option<String>,
+    barcode: Option<String>,
+    /// Replaces the allergens, an empty list clears them
+    allergens: Option<Vec<String>>,
+    /// Replaces the diet properties, an empty list marks the ingredient as having none of them
+    properties: Option<Vec<IngredientProperty>>,
+}
+
+pub async fn update_ingredient_item(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(ingredient_item): Json<IngredientItemUpdateInformation>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let barcode = ingredient_item
+        .barcode
+        .as_deref()
+        .map(parse_barcode)
+        .transpose()?;
+    let allergens = ingredient_item
+        .allergens
+        .as_deref()
+        .map(normalize_allergens);
+    let properties = ingredient_item.properties.as_deref().map(property_names);
+
+    if let Some(name) = &ingredient_item.name {
+        let existing = find_ingredient_by_name(&state.db, name)
             .await
-        } else {
-            sqlx::query_as!(
-                IngredientItem,
-                "SELECT id, name, creator_id FROM ingredient_items ORDER BY name ASC OFFSET $1 LIMIT $2",
-                start,
-                end
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to update ingredient item: {}", e),
+                )
+            })?;
+        if let Some((existing_id, existing_name)) = existing.filter(|(other, _)| *other != id) {
+            return Err(duplicate_error(name, existing_id, &existing_name));
+        }
+    }
+
+    let result = sqlx::query!(
+        r#"
+        UPDATE ingredient_items SET
+            name = COALESCE($1, name),
+            name_key = COALESCE($6, name_key),
+            store_section = COALESCE($2, store_section),
+            category_id = COALESCE($5, category_id),
+            barcode = COALESCE($7, barcode),
+            allergens = COALESCE($8, allergens),
+            properties = COALESCE($9, properties)
+        WHERE id = $3 AND creator_id = $4
+        "#,
+        ingredient_item.name,
+        ingredient_item
+            .store_section
+            .as_deref()
+            .map(normalize_section),
+        id,
+        user.id,
+        ingredient_item.category_id,
+        ingredient_item.name.as_deref().map(ingredient_key),
+        barcode,
+        allergens.as_deref(),
+        properties.as_deref()
+    )
+    .execute(&state.db)
+    .await
+    .map_err(barcode_error("Failed to update ingredient item"))?;
+
+    if result.rows_affected() == 0 {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Ingredient item not found".to_string(),
+        ));
+    }
+
+    if allergens.is_some() || properties.is_some() {
+        let mut conn = state.db.acquire().await.map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to update ingredient item: {}", e),
             )
-            .fetch_all(&state.db)
+        })?;
+        refresh_ingredient_meal_diets(&mut conn, &[id])
             .await
-        }
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to update meal diets: {}", e),
+                )
+            })?;
     }
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get ingredient items: {}", e)))?;
 
-    Ok(Json(IngredientItemsResponse {
-        ingredient_items: query,
-    }))
-} 
+    Ok(StatusCode::OK)
+}
+
+#[derive(Serialize)]
+pub struct IngredientDependent {
+    pub id: Uuid,
+    pub name: String,
+    pub creator_id: Uuid,
+}
+#[derive(Serialize)]
+pub struct IngredientDependentsResponse {
+    meal_items: Vec<IngredientDependent>,
+}
+
+async fn find_ingredient_dependents(
+    db: impl PgExecutor<'_>,
+    id: Uuid,
+) -> Result<Vec<IngredientDependent>, sqlx::Error> {
+    sqlx::query_as!(
+        IngredientDependent,
+        "SELECT id, name, creator_id FROM meal_items WHERE ingredient_items @> ARRAY[$1::uuid] ORDER BY name ASC",
+        id
+    )
+    .fetch_all(db)
+    .await
+}
+
+pub async fn get_ingredient_dependents(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<IngredientDependentsResponse>, (StatusCode, String)> {
+    let meal_items = find_ingredient_dependents(&state.db, id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to get ingredient dependents: {}", e),
+            )
+        })?;
+
+    Ok(Json(IngredientDependentsResponse { meal_items }))
+}
+
+#[derive(Deserialize)]
+pub struct DeleteIngredientItemInformation {
+    #[serde(default)]
+    mode: DeleteMode,
+}
 
 pub async fn delete_ingredient_item(
     Extension((user, _token)): Extension<(User, String)>,
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
+    Query(params): Query<DeleteIngredientItemInformation>,
 ) -> Result<StatusCode, (StatusCode, String)> {
+    let error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to delete ingredient item: {}", e),
+        )
+    };
+    let mut tx = state.db.begin().await.map_err(error)?;
+
     // Only allow deletion if user is the creator
+    let ingredient = sqlx::query!(
+        "SELECT name FROM ingredient_items WHERE id = $1 AND creator_id = $2 FOR UPDATE",
+        id,
+        user.id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(error)?
+    .ok_or((
+        StatusCode::NOT_FOUND,
+        "Ingredient item not found".to_string(),
+    ))?;
+
+    let dependents = find_ingredient_dependents(&mut *tx, id)
+        .await
+        .map_err(error)?;
+    let dependent_ids: Vec<Uuid> = dependents.iter().map(|meal| meal.id).collect();
+
+    if !dependents.is_empty() {
+        match params.mode {
+            DeleteMode::Restrict => {
+                let dependents: Vec<(Uuid, String)> = dependents
+                    .into_iter()
+                    .map(|meal| (meal.id, meal.name))
+                    .collect();
+                return Err((
+                    StatusCode::CONFLICT,
+                    dependents_message(&ingredient.name, "meal", &dependents),
+                ));
+            }
+            DeleteMode::Nullify => {
+                sqlx::query!(
+                    r#"
+                    UPDATE meal_items SET
+                        ingredient_items = array_remove(ingredient_items, $1),
+                        update_date = NOW()
+                    WHERE id = ANY($2)
+                    "#,
+                    id,
+                    &dependent_ids
+                )
+                .execute(&mut *tx)
+                .await
+                .map_err(error)?;
+                refresh_meal_diets(&mut tx, &dependent_ids)
+                    .await
+                    .map_err(error)?;
+            }
+            DeleteMode::Cascade => {
+                if let Some(meal) = dependents.iter().find(|meal| meal.creator_id != user.id) {
+                    return Err((
+                        StatusCode::FORBIDDEN,
+                        format!(
+                            "\"{}\" belongs to another user and cannot be deleted with the ingredient",
+                            meal.name
+                        ),
+                    ));
+                }
+                // Meals deleted this way take their calendar entries with them
+                sqlx::query!(
+                    "DELETE FROM calendar_items WHERE meal_item_id = ANY($1)",
+                    &dependent_ids
+                )
+                .execute(&mut *tx)
+                .await
+                .map_err(error)?;
+                // Links between deleted meals go too, meals outside the deletion only lose the component
+                let parent_ids: Vec<Uuid> = sqlx::query_scalar!(
+                    r#"
+                    DELETE FROM meal_item_components
+                    WHERE component_id = ANY($1) OR meal_item_id = ANY($1)
+                    RETURNING meal_item_id
+                    "#,
+                    &dependent_ids
+                )
+                .fetch_all(&mut *tx)
+                .await
+                .map_err(error)?
+                .into_iter()
+                .filter(|id| !dependent_ids.contains(id))
+                .collect();
+                sqlx::query!("DELETE FROM meal_items WHERE id = ANY($1)", &dependent_ids)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(error)?;
+                refresh_meal_diets(&mut tx, &parent_ids)
+                    .await
+                    .map_err(error)?;
+            }
+        }
+    }
+
+    sqlx::query!("DELETE FROM ingredient_items WHERE id = $1", id)
+        .execute(&mut *tx)
+        .await
+        .map_err(error)?;
+
+    tx.commit().await.map_err(error)?;
+    Ok(StatusCode::OK)
+}
+
+#[derive(Serialize)]
+pub struct IngredientMatch {
+    pub id: Uuid,
+    pub name: String,
+    pub score: f32,
+}
+
+pub async fn find_ingredient_matches(
+    db: impl PgExecutor<'_>,
+    name: &str,
+    limit: i64,
+) -> Result<Vec<IngredientMatch>, sqlx::Error> {
+    // Trigram similarity tolerates typos and plurals, "tomatoes" still finds "Tomato".
+    // Aliases match on behalf of their ingredient and a same-key name is a perfect match.
+    sqlx::query_as!(
+        IngredientMatch,
+        r#"
+        SELECT f.id, f.name, max(GREATEST(
+            similarity(lower(f.name), lower($1)),
+            COALESCE(similarity(lower(a.name), lower($1)), 0),
+            (f.name_key = $3 OR a.name_key = $3)::int::real
+        )) as "score!"
+        FROM ingredient_items f
+        LEFT JOIN ingredient_aliases a ON a.ingredient_item_id = f.id
+        WHERE lower(f.name) % lower($1)
+            OR lower(a.name) % lower($1)
+            OR f.name_key = $3
+            OR a.name_key = $3
+        GROUP BY f.id, f.name
+        ORDER BY 3 DESC, length(f.name) ASC
+        LIMIT $2
+        "#,
+        name,
+        limit,
+        ingredient_key(name)
+    )
+    .fetch_all(db)
+    .await
+}
+
+#[derive(Deserialize)]
+pub struct ParseIngredientLineInformation {
+    line: String,
+}
+#[derive(Serialize)]
+pub struct ParseIngredientLineResponse {
+    #[serde(flatten)]
+    parsed: ParsedIngredient,
+    matches: Vec<IngredientMatch>,
+}
+
+pub async fn parse_ingredient_item_line(
+    State(state): State<AppState>,
+    Query(params): Query<ParseIngredientLineInformation>,
+) -> Result<Json<ParseIngredientLineResponse>, (StatusCode, String)> {
+    let parsed = parse_ingredient_line(&params.line);
+
+    let matches = if parsed.ingredient.is_empty() {
+        Vec::new()
+    } else {
+        find_ingredient_matches(&state.db, &parsed.ingredient, 5)
+            .await
+            .map_err(|e| {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Failed to match ingredient: {}", e),
+                )
+            })?
+    };
+
+    Ok(Json(ParseIngredientLineResponse { parsed, matches }))
+}
+
+#[derive(Serialize)]
+pub struct IngredientAlias {
+    pub id: Uuid,
+    pub name: String,
+    pub creator_id: Uuid,
+}
+#[derive(Serialize)]
+pub struct IngredientAliasesResponse {
+    aliases: Vec<IngredientAlias>,
+}
+
+pub async fn get_ingredient_aliases(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<IngredientAliasesResponse>, (StatusCode, String)> {
+    let aliases = sqlx::query_as!(
+        IngredientAlias,
+        "SELECT id, name, creator_id FROM ingredient_aliases WHERE ingredient_item_id = $1 ORDER BY name ASC",
+        id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get ingredient aliases: {}", e)))?;
+
+    Ok(Json(IngredientAliasesResponse { aliases }))
+}
+
+#[derive(Deserialize)]
+pub struct IngredientAliasCreationInformation {
+    name: String,
+}
+
+pub async fn create_ingredient_alias(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(alias): Json<IngredientAliasCreationInformation>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to create ingredient alias: {}", e),
+        )
+    };
+
+    // An alias cannot take a name that already points somewhere, including this ingredient
+    if let Some((existing_id, existing_name)) = find_ingredient_by_name(&state.db, &alias.name)
+        .await
+        .map_err(error)?
+    {
+        return Err(duplicate_error(&alias.name, existing_id, &existing_name));
+    }
+
     let result = sqlx::query!(
-        "DELETE FROM ingredient_items WHERE id = $1 AND creator_id = $2",
+        r#"
+        INSERT INTO ingredient_aliases (ingredient_item_id, name, name_key, creator_id)
+        SELECT id, $2, $3, $4 FROM ingredient_items WHERE id = $1
+        ON CONFLICT (name_key) DO NOTHING
+        "#,
+        id,
+        alias.name,
+        ingredient_key(&alias.name),
+        user.id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(error)?;
+
+    if result.rows_affected() == 0 {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Ingredient item not found".to_string(),
+        ));
+    }
+
+    Ok(StatusCode::OK)
+}
+
+pub async fn delete_ingredient_alias(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path((id, alias_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    // The alias creator or the ingredient creator may remove an alias
+    sqlx::query!(
+        r#"
+        DELETE FROM ingredient_aliases a
+        USING ingredient_items f
+        WHERE a.id = $1 AND a.ingredient_item_id = $2 AND f.id = a.ingredient_item_id
+        AND (a.creator_id = $3 OR f.creator_id = $3)
+        "#,
+        alias_id,
         id,
         user.id
     )
@@ -124,13 +720,385 @@ pub async fn delete_ingredient_item(
     .map_err(|e| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to delete ingredient item: {}", e),
+            format!("Failed to delete ingredient alias: {}", e),
         )
     })?;
 
-    if result.rows_affected() == 0 {
-        return Err((StatusCode::NOT_FOUND, "Ingredient item not found".to_string()));
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct IngredientMergeInformation {
+    from: Vec<Uuid>,
+}
+
+pub async fn merge_ingredient_items(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(merge): Json<IngredientMergeInformation>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to merge ingredient items: {}", e),
+        )
+    };
+    let mut from = merge.from;
+    from.sort();
+    from.dedup();
+    if from.contains(&id) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "An ingredient cannot be merged into itself".to_string(),
+        ));
+    }
+    if from.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Nothing to merge into the ingredient".to_string(),
+        ));
+    }
+
+    let mut tx = state.db.begin().await.map_err(error)?;
+
+    let target = sqlx::query!(
+        "SELECT name_key FROM ingredient_items WHERE id = $1 FOR UPDATE",
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(error)?
+    .ok_or((
+        StatusCode::NOT_FOUND,
+        "Ingredient item not found".to_string(),
+    ))?;
+
+    // Anyone may fold a same-named duplicate away, other merges need the source's creator
+    let sources = sqlx::query!(
+        r#"
+        SELECT id, name, name_key, creator_id, calories, protein, fat, carbohydrates, fiber,
+        sugar, sodium, density, piece_weight, reference_food_id, barcode
+        FROM ingredient_items WHERE id = ANY($1)
+        ORDER BY creation_date ASC
+        FOR UPDATE
+        "#,
+        &from
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(error)?;
+    if sources.len() != from.len() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Ingredient item not found".to_string(),
+        ));
+    }
+    if let Some(source) = sources
+        .iter()
+        .find(|source| source.creator_id != user.id && source.name_key != target.name_key)
+    {
+        return Err((
+            StatusCode::FORBIDDEN,
+            format!(
+                "Only the creator of \"{}\" can merge it into another ingredient",
+                source.name
+            ),
+        ));
+    }
+
+    // Whatever the target does not know yet comes from the sources, the oldest first.
+    // A barcode can only be on one ingredient, so the sources let go of theirs first.
+    let first = |field: fn(&_) -> Option<f64>| sources.iter().find_map(field);
+    let (calories, protein, fat, carbohydrates, fiber, sugar, sodium) = (
+        first(|source| source.calories),
+        first(|source| source.protein),
+        first(|source| source.fat),
+        first(|source| source.carbohydrates),
+        first(|source| source.fiber),
+        first(|source| source.sugar),
+        first(|source| source.sodium),
+    );
+    let (density, piece_weight) = (
+        first(|source| source.density),
+        first(|source| source.piece_weight),
+    );
+    let reference_food_id = sources.iter().find_map(|source| source.reference_food_id);
+    let barcode = sources.iter().find_map(|source| source.barcode.clone());
+    sqlx::query!(
+        "UPDATE ingredient_items SET barcode = NULL WHERE id = ANY($1)",
+        &from
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
+    let target = sqlx::query!(
+        r#"
+        UPDATE ingredient_items SET
+            calories = COALESCE(calories, $2),
+            protein = COALESCE(protein, $3),
+            fat = COALESCE(fat, $4),
+            carbohydrates = COALESCE(carbohydrates, $5),
+            fiber = COALESCE(fiber, $6),
+            sugar = COALESCE(sugar, $7),
+            sodium = COALESCE(sodium, $8),
+            density = COALESCE(density, $9),
+            piece_weight = COALESCE(piece_weight, $10),
+            reference_food_id = COALESCE(reference_food_id, $11),
+            barcode = COALESCE(barcode, $12)
+        WHERE id = $1
+        RETURNING name, name_key, density, piece_weight
+        "#,
+        id,
+        calories,
+        protein,
+        fat,
+        carbohydrates,
+        fiber,
+        sugar,
+        sodium,
+        density,
+        piece_weight,
+        reference_food_id,
+        barcode
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(error)?;
+
+    // Each meal keeps its ingredient order, with the target where the first source was
+    sqlx::query!(
+        r#"
+        UPDATE meal_items SET
+            ingredient_items = ARRAY(
+                SELECT item FROM (
+                    SELECT CASE WHEN u = ANY($2) THEN $1 ELSE u END as item, min(n) as n
+                    FROM unnest(ingredient_items) WITH ORDINALITY t(u, n)
+                    GROUP BY 1
+                ) s
+                ORDER BY n
+            ),
+            update_date = NOW()
+        WHERE ingredient_items && $2
+        "#,
+        id,
+        &from
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
+
+    // Amounts of the merged duplicates in a meal add up, a meal keeps one amount per ingredient
+    // so the unit the target already uses wins, otherwise the one with the largest total
+    let amounts = sqlx::query!(
+        r#"
+        SELECT a.meal_item_id, m.name, a.ingredient_item_id, a.quantity, a.unit
+        FROM meal_item_amounts a
+        JOIN meal_items m ON m.id = a.meal_item_id
+        WHERE a.ingredient_item_id = $1 OR a.ingredient_item_id = ANY($2)
+        ORDER BY a.meal_item_id
+        "#,
+        id,
+        &from
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(error)?;
+
+    let (mut meal_item_ids, mut quantities, mut units) = (Vec::new(), Vec::new(), Vec::new());
+    for meal in amounts.chunk_by(|a, b| a.meal_item_id == b.meal_item_id) {
+        if meal.iter().all(|amount| amount.ingredient_item_id == id) {
+            continue;
+        }
+        let unit = match meal.iter().find(|amount| amount.ingredient_item_id == id) {
+            Some(amount) => amount.unit.clone(),
+            None => {
+                let mut totals: Vec<(Option<String>, f64)> = Vec::new();
+                for amount in meal {
+                    match totals.iter_mut().find(|(unit, _)| *unit == amount.unit) {
+                        Some((_, total)) => *total += amount.quantity,
+                        None => totals.push((amount.unit.clone(), amount.quantity)),
+                    }
+                }
+                totals
+                    .into_iter()
+                    .max_by(|a, b| a.1.total_cmp(&b.1))
+                    .and_then(|(unit, _)| unit)
+            }
+        };
+        let mut total = 0.0;
+        for amount in meal {
+            let converted = convert_amount(
+                amount.quantity,
+                amount.unit.as_deref(),
+                unit.as_deref(),
+                target.density,
+                target.piece_weight,
+            )
+            .ok_or_else(|| {
+                (
+                    StatusCode::CONFLICT,
+                    format!(
+                        "\"{}\" measures {} in both {} and {}, give it a density or piece weight to add them up",
+                        amount.name,
+                        target.name,
+                        amount.unit.as_deref().unwrap_or("pieces"),
+                        unit.as_deref().unwrap_or("pieces")
+                    ),
+                )
+            })?;
+            total += converted;
+        }
+        meal_item_ids.push(meal[0].meal_item_id);
+        quantities.push(total);
+        units.push(unit);
+    }
+    sqlx::query!(
+        "DELETE FROM meal_item_amounts WHERE ingredient_item_id = ANY($1)",
+        &from
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
+    sqlx::query!(
+        r#"
+        INSERT INTO meal_item_amounts (meal_item_id, ingredient_item_id, quantity, unit)
+        SELECT meal_item_id, $1, quantity, unit
+        FROM UNNEST($2::uuid[], $3::float8[], $4::varchar[]) t(meal_item_id, quantity, unit)
+        ON CONFLICT (meal_item_id, ingredient_item_id) DO UPDATE
+        SET quantity = EXCLUDED.quantity, unit = EXCLUDED.unit
+        "#,
+        id,
+        &meal_item_ids,
+        &quantities,
+        &units as &[Option<String>]
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
+
+    sqlx::query!(
+        "UPDATE pantry_items SET ingredient_item_id = $1 WHERE ingredient_item_id = ANY($2)",
+        id,
+        &from
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
+    // The target keeps its own seasons, the sources fill in the regions it has none for
+    sqlx::query!(
+        r#"
+        INSERT INTO ingredient_seasons (ingredient_item_id, region, months)
+        SELECT $1, region, months FROM ingredient_seasons WHERE ingredient_item_id = ANY($2)
+        ON CONFLICT (ingredient_item_id, region) DO NOTHING
+        "#,
+        id,
+        &from
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
+    sqlx::query!(
+        "UPDATE ingredient_prices SET ingredient_item_id = $1 WHERE ingredient_item_id = ANY($2)",
+        id,
+        &from
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
+    sqlx::query!(
+        "UPDATE shopping_list_items SET ingredient_item_id = $1 WHERE ingredient_item_id = ANY($2)",
+        id,
+        &from
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
+    sqlx::query!(
+        "UPDATE ingredient_aliases SET ingredient_item_id = $1 WHERE ingredient_item_id = ANY($2)",
+        id,
+        &from
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
+
+    // Substitutions follow the target, ones it already has or that would point at itself are dropped
+    sqlx::query!(
+        r#"
+        INSERT INTO ingredient_substitutions (ingredient_item_id, substitute_id, ratio, note, creator_id, creation_date)
+        SELECT
+            CASE WHEN ingredient_item_id = ANY($2) THEN $1 ELSE ingredient_item_id END,
+            CASE WHEN substitute_id = ANY($2) THEN $1 ELSE substitute_id END,
+            ratio, note, creator_id, creation_date
+        FROM ingredient_substitutions
+        WHERE (ingredient_item_id = ANY($2) OR substitute_id = ANY($2))
+        AND CASE WHEN ingredient_item_id = ANY($2) THEN $1 ELSE ingredient_item_id END
+            <> CASE WHEN substitute_id = ANY($2) THEN $1 ELSE substitute_id END
+        ON CONFLICT (ingredient_item_id, substitute_id) DO NOTHING
+        "#,
+        id,
+        &from
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
+
+    // The merged names live on as aliases so old spellings still resolve
+    for source in sources
+        .iter()
+        .filter(|source| source.name_key != target.name_key)
+    {
+        sqlx::query!(
+            r#"
+            INSERT INTO ingredient_aliases (ingredient_item_id, name, name_key, creator_id)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (name_key) DO NOTHING
+            "#,
+            id,
+            source.name,
+            source.name_key,
+            source.creator_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(error)?;
     }
 
+    // Losing an allergen or property tag in a merge would hide it from allergy and diet filtering
+    sqlx::query!(
+        r#"
+        UPDATE ingredient_items SET
+            allergens = ARRAY(
+                SELECT DISTINCT a FROM ingredient_items f, unnest(f.allergens) a
+                WHERE f.id = $1 OR f.id = ANY($2)
+                ORDER BY a
+            ),
+            properties = CASE WHEN EXISTS (
+                SELECT 1 FROM ingredient_items f
+                WHERE (f.id = $1 OR f.id = ANY($2)) AND f.properties IS NOT NULL
+            ) THEN ARRAY(
+                SELECT DISTINCT p FROM ingredient_items f, unnest(f.properties) p
+                WHERE f.id = $1 OR f.id = ANY($2)
+                ORDER BY p
+            ) END
+        WHERE id = $1
+        "#,
+        id,
+        &from
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
+
+    sqlx::query!("DELETE FROM ingredient_items WHERE id = ANY($1)", &from)
+        .execute(&mut *tx)
+        .await
+        .map_err(error)?;
+    refresh_ingredient_meal_diets(&mut tx, &[id])
+        .await
+        .map_err(error)?;
+
+    tx.commit().await.map_err(error)?;
     Ok(StatusCode::OK)
-}
\ No newline at end of file
+}
@@ -1,14 +1,190 @@
-use axum::extract::{Path, Query};
+use axum::extract::Path;
 use axum::{extract::State, http::StatusCode, Extension, Json};
-use backend::util::{app_state::AppState, user::User};
+use axum_extra::extract::Query;
+use backend::util::{
+    app_state::AppState,
+    components::find_component_cycle,
+    costs::{meal_cost, MealCost},
+    deletion::{dependents_message, is_foreign_key_violation, DeleteMode},
+    diets::{Diet, DietLabel},
+    equipment::{normalize_equipment, Difficulty},
+    nutrition::{meal_nutrition, MealNutrition},
+    search::{highlight, SearchHighlight},
+    seasons::{current_month, region_fallbacks, SeasonScore},
+    tags::{normalize_tags, Course, Cuisine},
+    units::normalize_unit,
+    user::User,
+};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgExecutor};
 use uuid::Uuid;
 
+use super::allergen::{load_meal_allergens, AllergyWarning};
+use super::diet::{load_meal_diets, refresh_meal_diets};
+use super::nutrition::load_nutrition_ingredients;
+use super::price::load_cost_ingredients;
+use super::season::{load_meal_season, load_user_region};
+
+#[derive(Deserialize)]
+pub struct MealItemAmount {
+    pub ingredient_id: Uuid,
+    pub quantity: f64,
+    pub unit: Option<String>,
+}
+
+/// Replaces how much of each ingredient a meal uses.
+pub async fn set_meal_item_amounts(
+    conn: &mut PgConnection,
+    meal_item_id: Uuid,
+    amounts: &[MealItemAmount],
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "DELETE FROM meal_item_amounts WHERE meal_item_id = $1",
+        meal_item_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    for amount in amounts {
+        sqlx::query!(
+            r#"
+            INSERT INTO meal_item_amounts (meal_item_id, ingredient_item_id, quantity, unit)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (meal_item_id, ingredient_item_id)
+            DO UPDATE SET quantity = EXCLUDED.quantity, unit = EXCLUDED.unit
+            "#,
+            meal_item_id,
+            amount.ingredient_id,
+            amount.quantity,
+            normalize_unit(amount.unit.as_deref())
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct MealItemComponent {
+    pub meal_item_id: Uuid,
+    /// Servings of the component used, defaults to 1
+    pub quantity: Option<f64>,
+}
+
+/// Replaces the meals used as components of a meal, in the given order.
+pub async fn set_meal_item_components(
+    conn: &mut PgConnection,
+    meal_item_id: Uuid,
+    components: &[MealItemComponent],
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "DELETE FROM meal_item_components WHERE meal_item_id = $1",
+        meal_item_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    for (position, component) in components.iter().enumerate() {
+        sqlx::query!(
+            r#"
+            INSERT INTO meal_item_components (meal_item_id, component_id, quantity, position)
+            VALUES ($1, $2, COALESCE($3::float8, 1), $4)
+            ON CONFLICT (meal_item_id, component_id) DO NOTHING
+            "#,
+            meal_item_id,
+            component.meal_item_id,
+            component.quantity,
+            position as i32
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Names the meals that would form a cycle if a meal used these components, `None` when it is fine.
+pub async fn find_meal_item_component_cycle(
+    conn: &mut PgConnection,
+    meal_item_id: Uuid,
+    component_ids: &[Uuid],
+) -> Result<Option<Vec<String>>, sqlx::Error> {
+    // The meal's current components are about to be replaced, so they are not followed
+    let edges: Vec<(Uuid, Uuid)> = sqlx::query!(
+        r#"
+        WITH RECURSIVE reachable (meal_item_id, component_id) AS (
+            SELECT c.meal_item_id, c.component_id FROM meal_item_components c
+            WHERE c.meal_item_id = ANY($2) AND c.meal_item_id <> $1
+            UNION
+            SELECT c.meal_item_id, c.component_id FROM meal_item_components c
+            JOIN reachable r ON c.meal_item_id = r.component_id
+            WHERE c.meal_item_id <> $1
+        )
+        SELECT meal_item_id as "meal_item_id!", component_id as "component_id!" FROM reachable
+        "#,
+        meal_item_id,
+        component_ids
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|edge| (edge.meal_item_id, edge.component_id))
+    .collect();
+
+    let Some(cycle) = find_component_cycle(meal_item_id, component_ids, &edges) else {
+        return Ok(None);
+    };
+    let names = sqlx::query!("SELECT id, name FROM meal_items WHERE id = ANY($1)", &cycle)
+        .fetch_all(&mut *conn)
+        .await?;
+
+    Ok(Some(
+        cycle
+            .iter()
+            .map(|id| {
+                names
+                    .iter()
+                    .find(|meal| meal.id == *id)
+                    .map(|meal| meal.name.clone())
+                    .unwrap_or_else(|| id.to_string())
+            })
+            .collect(),
+    ))
+}
+
+fn component_cycle_error(cycle: &[String]) -> (StatusCode, String) {
+    (
+        StatusCode::BAD_REQUEST,
+        format!("Components would form a cycle: {}", cycle.join(" → ")),
+    )
+}
+
 #[derive(Deserialize)]
 pub struct MealItemCreationInformation {
     name: String,
     ingredients: Vec<Uuid>,
     instructions: String,
+    servings: Option<i32>,
+    #[serde(default)]
+    amounts: Vec<MealItemAmount>,
+    /// Minutes, like the other times
+    prep_time: Option<i32>,
+    cook_time: Option<i32>,
+    /// Defaults to prep and cook time added up, set it when there is resting or rising too
+    total_time: Option<i32>,
+    difficulty: Option<Difficulty>,
+    #[serde(default)]
+    equipment: Vec<String>,
+    cuisine: Option<Cuisine>,
+    course: Option<Course>,
+    /// Free-form, such as "weeknight" or "freezer friendly"
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Other meals used as sub-recipes, such as a pizza dough
+    #[serde(default)]
+    components: Vec<MealItemComponent>,
 }
 
 pub async fn create_meal_item(
@@ -16,21 +192,56 @@ pub async fn create_meal_item(
     State(state): State<AppState>,
     Json(meal_item): Json<MealItemCreationInformation>,
 ) -> Result<StatusCode, (StatusCode, String)> {
-    sqlx::query!(
-        "INSERT INTO meal_items (name, ingredient_items, instructions, creator_id) VALUES ($1, $2, $3, $4)",
+    let error = |e: sqlx::Error| {
+        if is_foreign_key_violation(&e) {
+            return (
+                StatusCode::BAD_REQUEST,
+                "Meal item references an ingredient or component that does not exist".to_string(),
+            );
+        }
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to create meal item: {}", e),
+        )
+    };
+    let mut tx = state.db.begin().await.map_err(error)?;
+
+    let meal = sqlx::query!(
+        r#"
+        INSERT INTO meal_items (name, ingredient_items, instructions, creator_id, servings, prep_time, cook_time, total_time, difficulty, equipment, cuisine, course, tags)
+        VALUES ($1, $2, $3, $4, COALESCE($5, 1), $6, $7, $8, $9, $10, $11, $12, $13)
+        RETURNING id
+        "#,
         meal_item.name,
         &meal_item.ingredients,
         meal_item.instructions,
-        user.id
+        user.id,
+        meal_item.servings,
+        meal_item.prep_time,
+        meal_item.cook_time,
+        meal_item.total_time,
+        meal_item.difficulty.map(|difficulty| difficulty.as_str()),
+        &normalize_equipment(&meal_item.equipment),
+        meal_item.cuisine.map(|cuisine| cuisine.as_str()),
+        meal_item.course.map(|course| course.as_str()),
+        &normalize_tags(&meal_item.tags)
     )
-    .execute(&state.db)
+    .fetch_one(&mut *tx)
     .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to create meal item: {}", e),
-        )
-    })?;
+    .map_err(error)?;
+
+    set_meal_item_amounts(&mut tx, meal.id, &meal_item.amounts)
+        .await
+        .map_err(error)?;
+    // A new meal has nothing using it yet, so its components cannot lead back to it
+    set_meal_item_components(&mut tx, meal.id, &meal_item.components)
+        .await
+        .map_err(error)?;
+    refresh_meal_diets(&mut tx, &[meal.id])
+        .await
+        .map_err(error)?;
+
+    tx.commit().await.map_err(error)?;
     Ok(StatusCode::OK)
 }
 
@@ -39,48 +250,320 @@ pub struct MealItems {
     pub id: Uuid,
     pub name: String,
     pub creator_id: Uuid,
+    /// Share of the seasonal ingredients in season this month in the user's region
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub season_score: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rank: Option<f64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub highlights: Vec<SearchHighlight>,
+}
+#[derive(Serialize, Deserialize)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: i64,
+}
+/// How many meals each cuisine, course and tag would list, such as "Italian (12)".
+/// Cuisine and course counts ignore their own filter so the other options stay visible.
+#[derive(Serialize, Deserialize)]
+pub struct MealFacets {
+    pub cuisine: Vec<FacetCount>,
+    pub course: Vec<FacetCount>,
+    pub tag: Vec<FacetCount>,
 }
 #[derive(Serialize)]
 pub struct MealItemsResponse {
     meal_items: Vec<MealItems>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    facets: Option<MealFacets>,
+}
+#[derive(Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum IngredientFilterMode {
+    Any,
+    #[default]
+    All,
 }
 #[derive(Deserialize)]
 pub struct GetMealItemsInformation {
     start: Option<i64>,
     end: Option<i64>,
     search: Option<String>,
+    #[serde(default)]
+    include: Vec<Uuid>,
+    #[serde(default)]
+    include_mode: IngredientFilterMode,
+    #[serde(default)]
+    exclude: Vec<Uuid>,
+    /// Ingredient categories the meal must use something from, sub-categories included
+    #[serde(default)]
+    category: Vec<Uuid>,
+    /// Diets the meal must fit, all of them
+    #[serde(default)]
+    diet: Vec<Diet>,
+    /// Only meals at least this in season, from 0 to 1
+    min_season_score: Option<f64>,
+    /// Lists the most in season meals first, after search relevance
+    #[serde(default)]
+    seasonal: bool,
+    /// Only meals ready within this many minutes, sub-recipes included, meals without times are left out
+    max_time: Option<i32>,
+    #[serde(default)]
+    difficulty: Vec<Difficulty>,
+    /// Equipment the meal must not need, such as "oven"
+    #[serde(default)]
+    without_equipment: Vec<String>,
+    /// Only meals the user's kitchen has all the equipment for
+    #[serde(default)]
+    my_kitchen: bool,
+    /// Meals of any of these cuisines
+    #[serde(default)]
+    cuisine: Vec<Cuisine>,
+    /// Meals of any of these courses
+    #[serde(default)]
+    course: Vec<Course>,
+    /// Tags the meal must have, all of them
+    #[serde(default)]
+    tag: Vec<String>,
 }
 
 pub async fn get_meal_items(
+    Extension((user, _token)): Extension<(User, String)>,
     state: State<AppState>,
     Query(params): Query<GetMealItemsInformation>,
 ) -> Result<Json<MealItemsResponse>, (StatusCode, String)> {
     let start = params.start.unwrap_or(0);
     let end = params.end.unwrap_or(10);
+    let search = params.search.filter(|search| !search.trim().is_empty());
+    let region = load_user_region(&state.db, user.id).await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get meal items: {}", e),
+        )
+    })?;
 
-    let query: Vec<MealItems> = if let Some(search) = params.search {
-        sqlx::query_as!(
-            MealItems,
-            "SELECT id, name, creator_id FROM meal_items WHERE name ILIKE $1 ORDER BY name ASC OFFSET $2 LIMIT $3",
-            format!("%{}%", search),
-            start,
-            end
+    // A search matches titles and ingredient names by trigram similarity and the
+    // instructions by full-text search, title matches weigh the most. Ingredient
+    // categories count as ingredient names, so "cheese" finds meals with cheddar.
+    // Ingredient, category, allergy, season, time and equipment filters look into sub-recipes
+    // as well, through what `refresh_meal_diets` stores on each meal.
+    // Meals with anything from the user's allergy profile are never listed.
+    // The page and the facet counts come from the same matches, every row carries the facets
+    // and an empty page is a single row without a meal.
+    let query = sqlx::query!(
+        r#"
+        WITH matched AS (
+            SELECT m.id, m.name, m.creator_id, m.instructions, m.ingredient_items,
+            m.cuisine, m.course, m.tags,
+            s.rank, season.score,
+            (cardinality($18::varchar[]) = 0 OR m.cuisine = ANY($18)) as cuisine_match,
+            (cardinality($19::varchar[]) = 0 OR m.course = ANY($19)) as course_match
+            FROM meal_items m
+            LEFT JOIN LATERAL (
+                SELECT GREATEST(
+                    similarity(lower(m.name), lower($1)) * 3 + (m.name ILIKE '%' || $1 || '%')::int,
+                    COALESCE((
+                        SELECT max(GREATEST(
+                            similarity(lower(f.name), lower($1)),
+                            (
+                                SELECT max(similarity(lower(c.name), lower($1)))
+                                FROM ingredient_category_ancestors a
+                                JOIN ingredient_categories c ON c.id = a.ancestor_id
+                                WHERE a.category_id = f.category_id
+                            )
+                        ))
+                        FROM ingredient_items f
+                        WHERE f.id = ANY(m.ingredient_items)
+                    ), 0) * 2,
+                    ts_rank(to_tsvector('english', m.instructions), plainto_tsquery('english', $1))
+                ) as rank
+                WHERE $1::text IS NOT NULL
+            ) s ON true
+            LEFT JOIN LATERAL (
+                SELECT round(
+                    (count(*) FILTER (WHERE $10 = ANY(x.months)))::numeric / NULLIF(count(*), 0), 2
+                )::float8 as score
+                FROM unnest(m.expanded_ingredient_items) f(id)
+                CROSS JOIN LATERAL (
+                    SELECT i.months FROM ingredient_seasons i
+                    WHERE i.ingredient_item_id = f.id AND i.region = ANY($11)
+                    ORDER BY array_position($11, i.region::text)
+                    LIMIT 1
+                ) x
+            ) season ON true
+            WHERE ($1::text IS NULL
+                OR lower(m.name) % lower($1)
+                OR m.name ILIKE '%' || $1 || '%'
+                OR to_tsvector('english', m.instructions) @@ plainto_tsquery('english', $1)
+                OR EXISTS (
+                    SELECT 1 FROM ingredient_items f
+                    WHERE f.id = ANY(m.ingredient_items)
+                    AND (lower(f.name) % lower($1)
+                        OR f.name ILIKE '%' || $1 || '%'
+                        OR EXISTS (
+                            SELECT 1 FROM ingredient_category_ancestors a
+                            JOIN ingredient_categories c ON c.id = a.ancestor_id
+                            WHERE a.category_id = f.category_id
+                            AND (lower(c.name) % lower($1) OR c.name ILIKE '%' || $1 || '%')
+                        )
+                    )
+                )
+            )
+            AND NOT EXISTS (
+                SELECT 1 FROM unnest($7::uuid[]) k(category_id)
+                WHERE NOT EXISTS (
+                    SELECT 1 FROM ingredient_items f
+                    JOIN ingredient_category_ancestors a ON a.category_id = f.category_id
+                    WHERE f.id = ANY(m.expanded_ingredient_items) AND a.ancestor_id = k.category_id
+                )
+            )
+            AND (cardinality($4::uuid[]) = 0
+                OR ($5 AND m.expanded_ingredient_items && $4)
+                OR (NOT $5 AND m.expanded_ingredient_items @> $4)
+            )
+            AND NOT m.expanded_ingredient_items && $6::uuid[]
+            AND NOT EXISTS (
+                SELECT 1 FROM ingredient_items f
+                JOIN user_allergies u ON u.allergen = ANY(f.allergens)
+                WHERE f.id = ANY(m.expanded_ingredient_items) AND u.user_id = $8
+            )
+            AND m.diets @> $9::varchar[]
+            AND ($12::float8 IS NULL OR season.score >= $12)
+            AND ($14::int IS NULL OR m.expanded_ready_time <= $14)
+            AND (cardinality($15::varchar[]) = 0 OR m.difficulty = ANY($15))
+            AND NOT m.expanded_equipment && $16::varchar[]
+            AND (NOT $17 OR m.expanded_equipment <@ ARRAY(
+                SELECT e.equipment FROM user_equipment e WHERE e.user_id = $8
+            )::varchar[])
+            AND m.tags @> $20::varchar[]
+        ),
+        facets AS (
+            SELECT json_build_object(
+                'cuisine', (
+                    SELECT COALESCE(json_agg(json_build_object('value', cuisine, 'count', count) ORDER BY count DESC, cuisine), '[]'::json)
+                    FROM (
+                        SELECT cuisine, count(*) FROM matched
+                        WHERE cuisine IS NOT NULL AND course_match
+                        GROUP BY cuisine
+                    ) x
+                ),
+                'course', (
+                    SELECT COALESCE(json_agg(json_build_object('value', course, 'count', count) ORDER BY count DESC, course), '[]'::json)
+                    FROM (
+                        SELECT course, count(*) FROM matched
+                        WHERE course IS NOT NULL AND cuisine_match
+                        GROUP BY course
+                    ) x
+                ),
+                'tag', (
+                    SELECT COALESCE(json_agg(json_build_object('value', tag, 'count', count) ORDER BY count DESC, tag), '[]'::json)
+                    FROM (
+                        SELECT t.tag, count(*) FROM matched
+                        CROSS JOIN LATERAL unnest(matched.tags) t(tag)
+                        WHERE cuisine_match AND course_match
+                        GROUP BY t.tag
+                    ) x
+                )
+            ) as facets
         )
-        .fetch_all(&state.db)
-        .await
-    } else {
-        sqlx::query_as!(
-            MealItems,
-            "SELECT id, name, creator_id FROM meal_items ORDER BY name ASC OFFSET $1 LIMIT $2",
-            start,
-            end
+        SELECT p.id as "id?", p.name as "name?", p.creator_id as "creator_id?",
+        p.instructions as "instructions?",
+        ARRAY(
+            SELECT f.name FROM ingredient_items f WHERE f.id = ANY(p.ingredient_items)
+        ) as "ingredient_names!",
+        p.rank as "rank?: f64",
+        p.score as "season_score?: f64",
+        facets.facets as "facets!"
+        FROM facets
+        LEFT JOIN LATERAL (
+            SELECT * FROM matched
+            WHERE cuisine_match AND course_match
+            ORDER BY rank DESC NULLS LAST, CASE WHEN $13 THEN score END DESC NULLS LAST, name ASC
+            OFFSET $2 LIMIT $3
+        ) p ON true
+        "#,
+        search,
+        start,
+        end,
+        &params.include,
+        params.include_mode == IngredientFilterMode::Any,
+        &params.exclude,
+        &params.category,
+        user.id,
+        &params
+            .diet
+            .iter()
+            .map(|diet| diet.as_str().to_string())
+            .collect::<Vec<_>>(),
+        current_month(),
+        &region_fallbacks(&region),
+        params.min_season_score,
+        params.seasonal,
+        params.max_time,
+        &params
+            .difficulty
+            .iter()
+            .map(|difficulty| difficulty.as_str().to_string())
+            .collect::<Vec<_>>(),
+        &normalize_equipment(&params.without_equipment),
+        params.my_kitchen,
+        &params
+            .cuisine
+            .iter()
+            .map(|cuisine| cuisine.as_str().to_string())
+            .collect::<Vec<_>>(),
+        &params
+            .course
+            .iter()
+            .map(|course| course.as_str().to_string())
+            .collect::<Vec<_>>(),
+        &normalize_tags(&params.tag)
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get meal items: {}", e),
         )
-        .fetch_all(&state.db)
-        .await
-    }
-    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get meal items: {}", e)))?;
+    })?;
 
-    Ok(Json(MealItemsResponse { meal_items: query }))
+    let facets = query
+        .first()
+        .map(|row| serde_json::from_value(row.facets.clone()).unwrap());
+    let meal_items = query
+        .into_iter()
+        .filter_map(|meal| {
+            let (id, name, creator_id, instructions) =
+                (meal.id?, meal.name?, meal.creator_id?, meal.instructions?);
+            let highlights = match search.as_deref() {
+                Some(search) => std::iter::once(highlight("name", &name, search))
+                    .chain(
+                        meal.ingredient_names
+                            .iter()
+                            .map(|name| highlight("ingredient", name, search)),
+                    )
+                    .chain(std::iter::once(highlight(
+                        "instructions",
+                        &instructions,
+                        search,
+                    )))
+                    .flatten()
+                    .collect(),
+                None => Vec::new(),
+            };
+            Some(MealItems {
+                id,
+                name,
+                creator_id,
+                season_score: meal.season_score,
+                rank: meal.rank,
+                highlights,
+            })
+        })
+        .collect();
+
+    Ok(Json(MealItemsResponse { meal_items, facets }))
 }
 
 #[derive(Serialize)]
@@ -88,30 +571,98 @@ pub struct MealItem {
     pub id: Uuid,
     pub name: String,
     pub ingredient_items: Vec<FoodItem>,
+    /// Sub-recipes, whose ingredients count towards nutrition, cost and diets
+    pub components: Vec<MealComponent>,
     pub instructions: String,
+    pub servings: i32,
+    pub prep_time: Option<i32>,
+    pub cook_time: Option<i32>,
+    /// The given total time, otherwise prep and cook time added up
+    pub total_time: Option<i32>,
+    pub difficulty: Option<Difficulty>,
+    pub equipment: Vec<String>,
+    pub cuisine: Option<Cuisine>,
+    pub course: Option<Course>,
+    pub tags: Vec<String>,
+    pub nutrition: MealNutrition,
+    /// Priced with the user's own ingredient prices
+    pub cost: MealCost,
+    /// How in season the ingredients are this month in the user's region
+    pub season: SeasonScore,
+    pub allergens: Vec<String>,
+    /// Ingredients with allergens from the user's allergy profile
+    pub allergy_warnings: Vec<AllergyWarning>,
+    pub diets: Vec<DietLabel>,
+    pub creator_id: Uuid,
+    pub forked_from: Option<MealItemForkSource>,
+}
+#[derive(Serialize, Deserialize)]
+pub struct MealComponent {
+    pub id: Uuid,
+    pub name: String,
+    /// Servings of the component used
+    pub quantity: f64,
+    /// Servings the component makes
+    pub servings: i32,
+}
+#[derive(Serialize)]
+pub struct MealItemForkSource {
+    // The original can be deleted after forking, the author is kept for attribution
+    pub id: Option<Uuid>,
+    pub name: Option<String>,
+    pub creator_id: Uuid,
+    pub creator_name: String,
+    pub upstream_changed: bool,
 }
 #[derive(Serialize, Deserialize)]
 pub struct FoodItem {
     pub id: Uuid,
     pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
 }
 
 pub async fn get_meal_item(
+    Extension((user, _token)): Extension<(User, String)>,
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
 ) -> Result<Json<MealItem>, (StatusCode, String)> {
     let meal = sqlx::query!(
         r#"
-        SELECT m.id, m.name, m.instructions,
+        SELECT m.id, m.name, m.instructions, m.servings, m.creator_id,
+        m.prep_time, m.cook_time, m.ready_time, m.difficulty, m.equipment,
+        m.cuisine, m.course, m.tags,
         (
             SELECT COALESCE(
-                json_agg(json_build_object('id', f.id, 'name', f.name)), 
+                json_agg(json_build_object(
+                    'id', f.id, 'name', f.name, 'quantity', a.quantity, 'unit', a.unit
+                )),
                 '[]'::json
             )
             FROM ingredient_items f
+            LEFT JOIN meal_item_amounts a ON a.meal_item_id = m.id AND a.ingredient_item_id = f.id
             WHERE f.id = ANY(m.ingredient_items)
-        ) as "ingredient_items!"
+        ) as "ingredient_items!",
+        (
+            SELECT COALESCE(
+                json_agg(json_build_object(
+                    'id', s.id, 'name', s.name, 'quantity', c.quantity, 'servings', s.servings
+                ) ORDER BY c.position),
+                '[]'::json
+            )
+            FROM meal_item_components c
+            JOIN meal_items s ON s.id = c.component_id
+            WHERE c.meal_item_id = m.id
+        ) as "components!",
+        m.forked_from, m.forked_from_creator_id,
+        o.name as "forked_from_name?",
+        u.first_name || ' ' || u.last_name as "forked_from_creator_name?",
+        COALESCE(o.update_date > m.fork_sync_date, false) as "upstream_changed!"
         FROM meal_items m
+        LEFT JOIN meal_items o ON o.id = m.forked_from
+        LEFT JOIN users u ON u.id = m.forked_from_creator_id
         WHERE m.id = $1
         "#,
         id
@@ -120,32 +671,692 @@ pub async fn get_meal_item(
     .await
     .map_err(|e| (StatusCode::NOT_FOUND, format!("Meal not found: {}", e)))?;
 
+    let forked_from = meal
+        .forked_from_creator_id
+        .map(|creator_id| MealItemForkSource {
+            id: meal.forked_from,
+            name: meal.forked_from_name,
+            creator_id,
+            creator_name: meal.forked_from_creator_name.unwrap_or_default(),
+            upstream_changed: meal.upstream_changed,
+        });
+
+    let ingredients = load_nutrition_ingredients(&state.db, meal.id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to get meal nutrition: {}", e),
+            )
+        })?;
+    let cost_ingredients = load_cost_ingredients(&state.db, meal.id, user.id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to get meal cost: {}", e),
+            )
+        })?;
+    let season_error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get meal season: {}", e),
+        )
+    };
+    let region = load_user_region(&state.db, user.id)
+        .await
+        .map_err(season_error)?;
+    let season = load_meal_season(&state.db, meal.id, &region, current_month())
+        .await
+        .map_err(season_error)?;
+    let (allergens, allergy_warnings) = load_meal_allergens(&state.db, meal.id, user.id)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to get meal allergens: {}", e),
+            )
+        })?;
+    let diets_error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get meal diets: {}", e),
+        )
+    };
+    let mut conn = state.db.acquire().await.map_err(diets_error)?;
+    let diets = load_meal_diets(&mut conn, meal.id)
+        .await
+        .map_err(diets_error)?;
+
     Ok(Json(MealItem {
         id: meal.id,
         name: meal.name,
         ingredient_items: serde_json::from_value(meal.ingredient_items).unwrap(),
+        components: serde_json::from_value(meal.components).unwrap(),
         instructions: meal.instructions,
+        servings: meal.servings,
+        prep_time: meal.prep_time,
+        cook_time: meal.cook_time,
+        total_time: meal.ready_time,
+        difficulty: meal.difficulty.as_deref().and_then(Difficulty::parse),
+        equipment: meal.equipment,
+        cuisine: meal.cuisine.as_deref().and_then(Cuisine::parse),
+        course: meal.course.as_deref().and_then(Course::parse),
+        tags: meal.tags,
+        nutrition: meal_nutrition(&ingredients, meal.servings),
+        cost: meal_cost(&cost_ingredients, meal.servings),
+        season,
+        allergens,
+        allergy_warnings,
+        diets,
+        creator_id: meal.creator_id,
+        forked_from,
     }))
 }
 
-pub async fn delete_meal_item(
+#[derive(Deserialize)]
+pub struct MealItemUpdateInformation {
+    name: Option<String>,
+    ingredients: Option<Vec<Uuid>>,
+    instructions: Option<String>,
+    servings: Option<i32>,
+    amounts: Option<Vec<MealItemAmount>>,
+    prep_time: Option<i32>,
+    cook_time: Option<i32>,
+    total_time: Option<i32>,
+    difficulty: Option<Difficulty>,
+    /// Replaces the equipment, an empty list clears it
+    equipment: Option<Vec<String>>,
+    cuisine: Option<Cuisine>,
+    course: Option<Course>,
+    /// Replaces the tags, an empty list clears them
+    tags: Option<Vec<String>>,
+    /// Replaces the components, an empty list clears them
+    components: Option<Vec<MealItemComponent>>,
+}
+
+pub async fn update_meal_item(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(meal_item): Json<MealItemUpdateInformation>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let error = |e: sqlx::Error| {
+        if is_foreign_key_violation(&e) {
+            return (
+                StatusCode::BAD_REQUEST,
+                "Meal item references an ingredient or component that does not exist".to_string(),
+            );
+        }
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to update meal item: {}", e),
+        )
+    };
+    let mut tx = state.db.begin().await.map_err(error)?;
+
+    let equipment = meal_item.equipment.as_deref().map(normalize_equipment);
+    let tags = meal_item.tags.as_deref().map(normalize_tags);
+    let result = sqlx::query!(
+        r#"
+        UPDATE meal_items SET
+            name = COALESCE($1, name),
+            ingredient_items = COALESCE($2, ingredient_items),
+            instructions = COALESCE($3, instructions),
+            servings = COALESCE($6, servings),
+            prep_time = COALESCE($7, prep_time),
+            cook_time = COALESCE($8, cook_time),
+            total_time = COALESCE($9, total_time),
+            difficulty = COALESCE($10, difficulty),
+            equipment = COALESCE($11, equipment),
+            cuisine = COALESCE($12, cuisine),
+            course = COALESCE($13, course),
+            tags = COALESCE($14, tags),
+            update_date = NOW()
+        WHERE id = $4 AND creator_id = $5
+        "#,
+        meal_item.name,
+        meal_item.ingredients.as_deref(),
+        meal_item.instructions,
+        id,
+        user.id,
+        meal_item.servings,
+        meal_item.prep_time,
+        meal_item.cook_time,
+        meal_item.total_time,
+        meal_item.difficulty.map(|difficulty| difficulty.as_str()),
+        equipment.as_deref(),
+        meal_item.cuisine.map(|cuisine| cuisine.as_str()),
+        meal_item.course.map(|course| course.as_str()),
+        tags.as_deref()
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Meal item not found".to_string()));
+    }
+
+    if let Some(amounts) = meal_item.amounts {
+        set_meal_item_amounts(&mut tx, id, &amounts)
+            .await
+            .map_err(error)?;
+    }
+    if let Some(components) = meal_item.components {
+        let component_ids: Vec<Uuid> = components
+            .iter()
+            .map(|component| component.meal_item_id)
+            .collect();
+        if let Some(cycle) = find_meal_item_component_cycle(&mut tx, id, &component_ids)
+            .await
+            .map_err(error)?
+        {
+            return Err(component_cycle_error(&cycle));
+        }
+        set_meal_item_components(&mut tx, id, &components)
+            .await
+            .map_err(error)?;
+    }
+    refresh_meal_diets(&mut tx, &[id]).await.map_err(error)?;
+
+    tx.commit().await.map_err(error)?;
+    Ok(StatusCode::OK)
+}
+
+#[derive(Serialize)]
+pub struct MealItemForkResponse {
+    id: Uuid,
+}
+
+pub async fn fork_meal_item(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<MealItemForkResponse>, (StatusCode, String)> {
+    let error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to fork meal item: {}", e),
+        )
+    };
+    let mut tx = state.db.begin().await.map_err(error)?;
+
+    let fork = sqlx::query!(
+        r#"
+        INSERT INTO meal_items (name, ingredient_items, instructions, servings, prep_time, cook_time, total_time, difficulty, equipment, cuisine, course, tags, creator_id, forked_from, forked_from_creator_id, fork_sync_date)
+        SELECT name, ingredient_items, instructions, servings, prep_time, cook_time, total_time, difficulty, equipment, cuisine, course, tags, $2, id, creator_id, update_date
+        FROM meal_items
+        WHERE id = $1
+        RETURNING id
+        "#,
+        id,
+        user.id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(error)?
+    .ok_or((StatusCode::NOT_FOUND, "Meal item not found".to_string()))?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO meal_item_amounts (meal_item_id, ingredient_item_id, quantity, unit)
+        SELECT $1, ingredient_item_id, quantity, unit FROM meal_item_amounts WHERE meal_item_id = $2
+        "#,
+        fork.id,
+        id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
+    // The fork uses the same sub-recipes, forking those is up to the user
+    sqlx::query!(
+        r#"
+        INSERT INTO meal_item_components (meal_item_id, component_id, quantity, position)
+        SELECT $1, component_id, quantity, position FROM meal_item_components WHERE meal_item_id = $2
+        "#,
+        fork.id,
+        id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
+    // Diet overrides stay with the original's creator, the fork starts from derived labels
+    refresh_meal_diets(&mut tx, &[fork.id])
+        .await
+        .map_err(error)?;
+
+    tx.commit().await.map_err(error)?;
+    Ok(Json(MealItemForkResponse { id: fork.id }))
+}
+
+pub async fn get_meal_item_forks(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<GetMealItemsInformation>,
+) -> Result<Json<MealItemsResponse>, (StatusCode, String)> {
+    let start = params.start.unwrap_or(0);
+    let end = params.end.unwrap_or(10);
+
+    let query = sqlx::query!(
+        "SELECT id, name, creator_id FROM meal_items WHERE forked_from = $1 ORDER BY creation_date ASC OFFSET $2 LIMIT $3",
+        id,
+        start,
+        end
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to get meal item forks: {}", e)))?;
+
+    let meal_items = query
+        .into_iter()
+        .map(|meal| MealItems {
+            id: meal.id,
+            name: meal.name,
+            creator_id: meal.creator_id,
+            season_score: None,
+            rank: None,
+            highlights: Vec::new(),
+        })
+        .collect();
+
+    Ok(Json(MealItemsResponse {
+        meal_items,
+        facets: None,
+    }))
+}
+
+pub async fn sync_meal_item_fork(
     Extension((user, _token)): Extension<(User, String)>,
     State(state): State<AppState>,
     Path(id): Path<Uuid>,
 ) -> Result<StatusCode, (StatusCode, String)> {
+    let error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to sync meal item fork: {}", e),
+        )
+    };
+    let mut tx = state.db.begin().await.map_err(error)?;
+
+    // The original may have since taken the fork on as a component
+    let upstream_components = sqlx::query_scalar!(
+        r#"
+        SELECT c.component_id FROM meal_items f
+        JOIN meal_item_components c ON c.meal_item_id = f.forked_from
+        WHERE f.id = $1 AND f.creator_id = $2
+        "#,
+        id,
+        user.id
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(error)?;
+    if let Some(cycle) = find_meal_item_component_cycle(&mut tx, id, &upstream_components)
+        .await
+        .map_err(error)?
+    {
+        return Err(component_cycle_error(&cycle));
+    }
+
+    // Pulling upstream replaces the fork's content with the original's current version
+    let result = sqlx::query!(
+        r#"
+        UPDATE meal_items f SET
+            name = o.name,
+            ingredient_items = o.ingredient_items,
+            instructions = o.instructions,
+            servings = o.servings,
+            prep_time = o.prep_time,
+            cook_time = o.cook_time,
+            total_time = o.total_time,
+            difficulty = o.difficulty,
+            equipment = o.equipment,
+            cuisine = o.cuisine,
+            course = o.course,
+            tags = o.tags,
+            fork_sync_date = o.update_date,
+            update_date = NOW()
+        FROM meal_items o
+        WHERE f.id = $1 AND f.creator_id = $2 AND o.id = f.forked_from
+        "#,
+        id,
+        user.id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
+
+    if result.rows_affected() == 0 {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Forked meal item not found".to_string(),
+        ));
+    }
+
+    sqlx::query!("DELETE FROM meal_item_amounts WHERE meal_item_id = $1", id)
+        .execute(&mut *tx)
+        .await
+        .map_err(error)?;
+    sqlx::query!(
+        r#"
+        INSERT INTO meal_item_amounts (meal_item_id, ingredient_item_id, quantity, unit)
+        SELECT f.id, a.ingredient_item_id, a.quantity, a.unit
+        FROM meal_items f
+        JOIN meal_item_amounts a ON a.meal_item_id = f.forked_from
+        WHERE f.id = $1
+        "#,
+        id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
+    sqlx::query!(
+        "DELETE FROM meal_item_components WHERE meal_item_id = $1",
+        id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
     sqlx::query!(
-        "DELETE FROM meal_items WHERE id = $1 AND creator_id = $2",
+        r#"
+        INSERT INTO meal_item_components (meal_item_id, component_id, quantity, position)
+        SELECT f.id, c.component_id, c.quantity, c.position
+        FROM meal_items f
+        JOIN meal_item_components c ON c.meal_item_id = f.forked_from
+        WHERE f.id = $1
+        "#,
+        id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
+    refresh_meal_diets(&mut tx, &[id]).await.map_err(error)?;
+
+    tx.commit().await.map_err(error)?;
+    Ok(StatusCode::OK)
+}
+
+#[derive(Serialize)]
+pub struct MealItemDependent {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+}
+#[derive(Serialize)]
+pub struct MealItemDependentsResponse {
+    calendar_items: Vec<MealItemDependent>,
+    /// Meals using this one as a component
+    meal_items: Vec<MealItemParent>,
+}
+#[derive(Serialize)]
+pub struct MealItemParent {
+    pub id: Uuid,
+    pub name: String,
+    pub creator_id: Uuid,
+}
+
+async fn find_meal_item_parents(
+    db: impl PgExecutor<'_>,
+    id: Uuid,
+) -> Result<Vec<MealItemParent>, sqlx::Error> {
+    sqlx::query_as!(
+        MealItemParent,
+        r#"
+        SELECT m.id, m.name, m.creator_id
+        FROM meal_item_components c
+        JOIN meal_items m ON m.id = c.meal_item_id
+        WHERE c.component_id = $1
+        ORDER BY m.name ASC
+        "#,
+        id
+    )
+    .fetch_all(db)
+    .await
+}
+
+async fn find_meal_item_dependents(
+    db: impl PgExecutor<'_>,
+    id: Uuid,
+) -> Result<Vec<MealItemDependent>, sqlx::Error> {
+    sqlx::query_as!(
+        MealItemDependent,
+        "SELECT id, user_id, start_date, end_date FROM calendar_items WHERE meal_item_id = $1 ORDER BY start_date ASC",
+        id
+    )
+    .fetch_all(db)
+    .await
+}
+
+pub async fn get_meal_item_dependents(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<MealItemDependentsResponse>, (StatusCode, String)> {
+    let error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get meal item dependents: {}", e),
+        )
+    };
+    let calendar_items = find_meal_item_dependents(&state.db, id)
+        .await
+        .map_err(error)?;
+    let meal_items = find_meal_item_parents(&state.db, id).await.map_err(error)?;
+
+    Ok(Json(MealItemDependentsResponse {
+        calendar_items,
+        meal_items,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct DeleteMealItemInformation {
+    #[serde(default)]
+    mode: DeleteMode,
+}
+
+pub async fn delete_meal_item(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<DeleteMealItemInformation>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to delete meal item: {}", e),
+        )
+    };
+    let mut tx = state.db.begin().await.map_err(error)?;
+
+    let meal = sqlx::query!(
+        "SELECT name FROM meal_items WHERE id = $1 AND creator_id = $2 FOR UPDATE",
         id,
         user.id
     )
-    .execute(&state.db)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(error)?
+    .ok_or((StatusCode::NOT_FOUND, "Meal item not found".to_string()))?;
+
+    let dependents = find_meal_item_dependents(&mut *tx, id)
+        .await
+        .map_err(error)?;
+
+    if !dependents.is_empty() {
+        match params.mode {
+            DeleteMode::Restrict => {
+                let dependents: Vec<(Uuid, String)> = dependents
+                    .into_iter()
+                    .map(|item| {
+                        (
+                            item.id,
+                            item.start_date.format("%Y-%m-%d %H:%M").to_string(),
+                        )
+                    })
+                    .collect();
+                return Err((
+                    StatusCode::CONFLICT,
+                    dependents_message(&meal.name, "calendar item", &dependents),
+                ));
+            }
+            DeleteMode::Nullify => {
+                sqlx::query!(
+                    "UPDATE calendar_items SET meal_item_id = NULL WHERE meal_item_id = $1",
+                    id
+                )
+                .execute(&mut *tx)
+                .await
+                .map_err(error)?;
+            }
+            DeleteMode::Cascade => {
+                sqlx::query!("DELETE FROM calendar_items WHERE meal_item_id = $1", id)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(error)?;
+            }
+        }
+    }
+
+    // Meals using this one as a sub-recipe only lose the component, they are never deleted
+    let parents = find_meal_item_parents(&mut *tx, id).await.map_err(error)?;
+    if !parents.is_empty() {
+        if params.mode == DeleteMode::Restrict {
+            let parents: Vec<(Uuid, String)> = parents
+                .into_iter()
+                .map(|parent| (parent.id, parent.name))
+                .collect();
+            return Err((
+                StatusCode::CONFLICT,
+                dependents_message(&meal.name, "meal", &parents),
+            ));
+        }
+        sqlx::query!(
+            "DELETE FROM meal_item_components WHERE component_id = $1",
+            id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(error)?;
+    }
+
+    sqlx::query!("DELETE FROM meal_items WHERE id = $1", id)
+        .execute(&mut *tx)
+        .await
+        .map_err(error)?;
+    refresh_meal_diets(
+        &mut tx,
+        &parents.iter().map(|parent| parent.id).collect::<Vec<_>>(),
+    )
+    .await
+    .map_err(error)?;
+
+    tx.commit().await.map_err(error)?;
+    Ok(StatusCode::OK)
+}
+
+/// Ingredients nearly every kitchen has, optionally left out when matching.
+const STAPLES: &[&str] = &[
+    "salt",
+    "pepper",
+    "black pepper",
+    "water",
+    "oil",
+    "olive oil",
+    "vegetable oil",
+    "cooking oil",
+];
+
+#[derive(Deserialize)]
+pub struct GetCookableMealItemsInformation {
+    start: Option<i64>,
+    end: Option<i64>,
+    #[serde(default)]
+    have: Vec<Uuid>,
+    ignore_staples: Option<bool>,
+    max_missing: Option<i64>,
+}
+#[derive(Serialize)]
+pub struct CookableMealItem {
+    pub id: Uuid,
+    pub name: String,
+    pub creator_id: Uuid,
+    pub needed: i64,
+    pub available: i64,
+    pub missing: Vec<FoodItem>,
+}
+#[derive(Serialize)]
+pub struct CookableMealItemsResponse {
+    meal_items: Vec<CookableMealItem>,
+}
+
+pub async fn get_cookable_meal_items(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Query(params): Query<GetCookableMealItemsInformation>,
+) -> Result<Json<CookableMealItemsResponse>, (StatusCode, String)> {
+    let start = params.start.unwrap_or(0);
+    let end = params.end.unwrap_or(10);
+    let staples: Vec<String> = STAPLES.iter().map(|staple| staple.to_string()).collect();
+
+    // Meals are ranked by the share of their ingredients on hand, then by how few are missing,
+    // the ingredients of sub-recipes count as the meal's own
+    let query = sqlx::query!(
+        r#"
+        SELECT m.id, m.name, m.creator_id,
+        count(f.id) as "needed!",
+        count(f.id) FILTER (WHERE f.id = ANY($1)) as "available!",
+        COALESCE(
+            json_agg(json_build_object('id', f.id, 'name', f.name) ORDER BY f.name)
+                FILTER (WHERE f.id <> ALL($1)),
+            '[]'::json
+        ) as "missing!"
+        FROM meal_items m
+        JOIN ingredient_items f ON f.id = ANY(m.expanded_ingredient_items)
+        WHERE NOT ($2 AND lower(f.name) = ANY($3))
+        AND NOT EXISTS (
+            SELECT 1 FROM ingredient_items x
+            JOIN user_allergies u ON u.allergen = ANY(x.allergens)
+            WHERE x.id = ANY(m.expanded_ingredient_items) AND u.user_id = $7
+        )
+        GROUP BY m.id, m.name, m.creator_id
+        HAVING count(f.id) FILTER (WHERE f.id = ANY($1)) > 0
+        AND count(f.id) FILTER (WHERE f.id <> ALL($1)) <= $4
+        ORDER BY
+            count(f.id) FILTER (WHERE f.id = ANY($1))::float / count(f.id) DESC,
+            count(f.id) FILTER (WHERE f.id <> ALL($1)) ASC,
+            m.name ASC
+        OFFSET $5 LIMIT $6
+        "#,
+        &params.have,
+        params.ignore_staples.unwrap_or(false),
+        &staples,
+        params.max_missing.unwrap_or(3),
+        start,
+        end,
+        user.id
+    )
+    .fetch_all(&state.db)
     .await
     .map_err(|e| {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to delete meal item: {}", e),
+            format!("Failed to get cookable meal items: {}", e),
         )
     })?;
 
-    Ok(StatusCode::OK)
+    let meal_items = query
+        .into_iter()
+        .map(|meal| CookableMealItem {
+            id: meal.id,
+            name: meal.name,
+            creator_id: meal.creator_id,
+            needed: meal.needed,
+            available: meal.available,
+            missing: serde_json::from_value(meal.missing).unwrap(),
+        })
+        .collect();
+
+    Ok(Json(CookableMealItemsResponse { meal_items }))
 }
@@ -1,3 +1,16 @@
-
+pub mod allergen;
+pub mod archive;
+pub mod barcode;
+pub mod category;
+pub mod diet;
+pub mod equipment;
+pub mod export;
+pub mod import;
 pub mod ingredient;
 pub mod meal;
+pub mod nutrition;
+pub mod price;
+pub mod reference_food;
+pub mod season;
+pub mod substitution;
+pub mod tag;
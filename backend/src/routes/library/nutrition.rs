@@ -0,0 +1,163 @@
+use axum::extract::Path;
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use backend::util::{
+    app_state::AppState,
+    nutrition::{NutritionFacts, NutritionIngredient},
+    user::User,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use super::diet::refresh_ingredient_meal_diets;
+
+/// Loads every ingredient of a meal and its components with its amount and nutrition facts.
+pub async fn load_nutrition_ingredients(
+    db: impl PgExecutor<'_>,
+    meal_item_id: Uuid,
+) -> Result<Vec<NutritionIngredient>, sqlx::Error> {
+    let query = sqlx::query!(
+        r#"
+        SELECT f.id, f.name, f.calories, f.protein, f.fat, f.carbohydrates, f.fiber, f.sugar,
+        f.sodium, f.density, f.piece_weight, a.quantity * e.scale as "quantity?", a.unit as "unit?"
+        FROM expand_meal_item($1) e
+        JOIN meal_items m ON m.id = e.meal_item_id
+        JOIN ingredient_items f ON f.id = ANY(m.ingredient_items)
+        LEFT JOIN meal_item_amounts a ON a.meal_item_id = m.id AND a.ingredient_item_id = f.id
+        ORDER BY f.name ASC
+        "#,
+        meal_item_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(query
+        .into_iter()
+        .map(|ingredient| NutritionIngredient {
+            id: ingredient.id,
+            name: ingredient.name,
+            facts: NutritionFacts {
+                calories: ingredient.calories,
+                protein: ingredient.protein,
+                fat: ingredient.fat,
+                carbohydrates: ingredient.carbohydrates,
+                fiber: ingredient.fiber,
+                sugar: ingredient.sugar,
+                sodium: ingredient.sodium,
+            },
+            quantity: ingredient.quantity,
+            unit: ingredient.unit,
+            density: ingredient.density,
+            piece_weight: ingredient.piece_weight,
+        })
+        .collect())
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct IngredientNutrition {
+    #[serde(flatten)]
+    facts: NutritionFacts,
+    density: Option<f64>,
+    piece_weight: Option<f64>,
+}
+
+pub async fn get_ingredient_nutrition(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<IngredientNutrition>, (StatusCode, String)> {
+    let ingredient = sqlx::query!(
+        r#"
+        SELECT calories, protein, fat, carbohydrates, fiber, sugar, sodium, density, piece_weight
+        FROM ingredient_items
+        WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get ingredient nutrition: {}", e),
+        )
+    })?
+    .ok_or((
+        StatusCode::NOT_FOUND,
+        "Ingredient item not found".to_string(),
+    ))?;
+
+    Ok(Json(IngredientNutrition {
+        facts: NutritionFacts {
+            calories: ingredient.calories,
+            protein: ingredient.protein,
+            fat: ingredient.fat,
+            carbohydrates: ingredient.carbohydrates,
+            fiber: ingredient.fiber,
+            sugar: ingredient.sugar,
+            sodium: ingredient.sodium,
+        },
+        density: ingredient.density,
+        piece_weight: ingredient.piece_weight,
+    }))
+}
+
+/// Replaces the nutrition facts of an ingredient, a missing field clears that value.
+pub async fn update_ingredient_nutrition(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(nutrition): Json<IngredientNutrition>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE ingredient_items SET
+            calories = $1, protein = $2, fat = $3, carbohydrates = $4, fiber = $5, sugar = $6,
+            sodium = $7, density = $8, piece_weight = $9
+        WHERE id = $10 AND creator_id = $11
+        "#,
+        nutrition.facts.calories,
+        nutrition.facts.protein,
+        nutrition.facts.fat,
+        nutrition.facts.carbohydrates,
+        nutrition.facts.fiber,
+        nutrition.facts.sugar,
+        nutrition.facts.sodium,
+        nutrition.density,
+        nutrition.piece_weight,
+        id,
+        user.id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to update ingredient nutrition: {}", e),
+        )
+    })?;
+
+    if result.rows_affected() == 0 {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Ingredient item not found".to_string(),
+        ));
+    }
+
+    // Keto depends on the nutrition, so the labels of meals using it may change
+    let mut conn = state.db.acquire().await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to update ingredient nutrition: {}", e),
+        )
+    })?;
+    refresh_ingredient_meal_diets(&mut conn, &[id])
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to update meal diets: {}", e),
+            )
+        })?;
+
+    Ok(StatusCode::OK)
+}
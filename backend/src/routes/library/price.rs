@@ -0,0 +1,201 @@
+use axum::extract::Path;
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use backend::util::{
+    app_state::AppState,
+    costs::{CostIngredient, Price},
+    units::normalize_unit,
+    user::User,
+};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+/// Loads every ingredient of a meal and its components with its amount and the user's latest price for it.
+pub async fn load_cost_ingredients(
+    db: impl PgExecutor<'_>,
+    meal_item_id: Uuid,
+    user_id: Uuid,
+) -> Result<Vec<CostIngredient>, sqlx::Error> {
+    let query = sqlx::query!(
+        r#"
+        SELECT f.id, f.name, f.density, f.piece_weight,
+        a.quantity * e.scale as "quantity?", a.unit as "unit?",
+        p.price as "price?", p.quantity as "price_quantity?", p.unit as "price_unit?"
+        FROM expand_meal_item($1) e
+        JOIN meal_items m ON m.id = e.meal_item_id
+        JOIN ingredient_items f ON f.id = ANY(m.ingredient_items)
+        LEFT JOIN meal_item_amounts a ON a.meal_item_id = m.id AND a.ingredient_item_id = f.id
+        LEFT JOIN LATERAL (
+            SELECT price, quantity, unit FROM ingredient_prices
+            WHERE user_id = $2 AND ingredient_item_id = f.id
+            ORDER BY price_date DESC, creation_date DESC
+            LIMIT 1
+        ) p ON true
+        ORDER BY f.name ASC
+        "#,
+        meal_item_id,
+        user_id
+    )
+    .fetch_all(db)
+    .await?;
+
+    Ok(query
+        .into_iter()
+        .map(|ingredient| CostIngredient {
+            id: ingredient.id,
+            name: ingredient.name,
+            quantity: ingredient.quantity,
+            unit: ingredient.unit,
+            density: ingredient.density,
+            piece_weight: ingredient.piece_weight,
+            price: ingredient.price.map(|price| Price {
+                price,
+                quantity: ingredient.price_quantity.unwrap_or(1.0),
+                unit: ingredient.price_unit,
+            }),
+        })
+        .collect())
+}
+
+#[derive(Serialize)]
+pub struct IngredientPrice {
+    pub id: Uuid,
+    pub price: f64,
+    pub quantity: f64,
+    pub unit: Option<String>,
+    pub store_id: Option<Uuid>,
+    pub store_name: Option<String>,
+    pub price_date: NaiveDate,
+}
+#[derive(Serialize)]
+pub struct IngredientPricesResponse {
+    prices: Vec<IngredientPrice>,
+}
+
+/// Lists the user's prices for an ingredient, the latest first since that is the one meals are costed with.
+pub async fn get_ingredient_prices(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<IngredientPricesResponse>, (StatusCode, String)> {
+    let prices = sqlx::query_as!(
+        IngredientPrice,
+        r#"
+        SELECT p.id, p.price, p.quantity, p.unit, p.store_id, s.name as "store_name?", p.price_date
+        FROM ingredient_prices p
+        LEFT JOIN stores s ON s.id = p.store_id
+        WHERE p.user_id = $1 AND p.ingredient_item_id = $2
+        ORDER BY p.price_date DESC, p.creation_date DESC
+        "#,
+        user.id,
+        id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get ingredient prices: {}", e),
+        )
+    })?;
+
+    Ok(Json(IngredientPricesResponse { prices }))
+}
+
+#[derive(Deserialize)]
+pub struct IngredientPriceCreationInformation {
+    price: f64,
+    /// How much the price buys, defaults to one `unit`
+    quantity: Option<f64>,
+    unit: Option<String>,
+    store_id: Option<Uuid>,
+    /// Defaults to today
+    price_date: Option<NaiveDate>,
+}
+#[derive(Serialize)]
+pub struct IngredientPriceCreationResponse {
+    id: Uuid,
+}
+
+/// Records what the user paid, older prices are kept as history.
+pub async fn create_ingredient_price(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(price): Json<IngredientPriceCreationInformation>,
+) -> Result<Json<IngredientPriceCreationResponse>, (StatusCode, String)> {
+    if price.price < 0.0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "The price cannot be negative".to_string(),
+        ));
+    }
+    if price.quantity.is_some_and(|quantity| quantity <= 0.0) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "The quantity must be greater than zero".to_string(),
+        ));
+    }
+
+    // Prices can only be filed under the user's own stores
+    let created = sqlx::query!(
+        r#"
+        INSERT INTO ingredient_prices (user_id, ingredient_item_id, store_id, price, quantity, unit, price_date)
+        SELECT $1, $2, $3, $4, COALESCE($5::float8, 1), $6, COALESCE($7::date, CURRENT_DATE)
+        WHERE $3::uuid IS NULL OR EXISTS (SELECT 1 FROM stores WHERE id = $3 AND user_id = $1)
+        RETURNING id
+        "#,
+        user.id,
+        id,
+        price.store_id,
+        price.price,
+        price.quantity,
+        normalize_unit(price.unit.as_deref()),
+        price.price_date
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| match e.as_database_error() {
+        Some(error) if error.is_foreign_key_violation() => (
+            StatusCode::NOT_FOUND,
+            "Ingredient item not found".to_string(),
+        ),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to create ingredient price: {}", e),
+        ),
+    })?
+    .ok_or((StatusCode::NOT_FOUND, "Store not found".to_string()))?;
+
+    Ok(Json(IngredientPriceCreationResponse { id: created.id }))
+}
+
+pub async fn delete_ingredient_price(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let result = sqlx::query!(
+        "DELETE FROM ingredient_prices WHERE id = $1 AND user_id = $2",
+        id,
+        user.id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to delete ingredient price: {}", e),
+        )
+    })?;
+
+    if result.rows_affected() == 0 {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Ingredient price not found".to_string(),
+        ));
+    }
+
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,249 @@
+use axum::extract::{Path, Query};
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use backend::util::{app_state::AppState, user::User};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::diet::refresh_ingredient_meal_diets;
+
+#[derive(Serialize)]
+pub struct ReferenceFood {
+    pub id: Uuid,
+    pub source: String,
+    pub source_id: String,
+    pub name: String,
+    pub brand: Option<String>,
+    pub barcode: Option<String>,
+    pub calories: Option<f64>,
+    pub protein: Option<f64>,
+    pub fat: Option<f64>,
+    pub carbohydrates: Option<f64>,
+    pub fiber: Option<f64>,
+    pub sugar: Option<f64>,
+    pub sodium: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f32>,
+}
+#[derive(Serialize)]
+pub struct ReferenceFoodsResponse {
+    reference_foods: Vec<ReferenceFood>,
+}
+#[derive(Deserialize)]
+pub struct SearchReferenceFoodsInformation {
+    search: String,
+    source: Option<String>,
+    limit: Option<i64>,
+}
+
+pub async fn search_reference_foods(
+    State(state): State<AppState>,
+    Query(params): Query<SearchReferenceFoodsInformation>,
+) -> Result<Json<ReferenceFoodsResponse>, (StatusCode, String)> {
+    let reference_foods = sqlx::query_as!(
+        ReferenceFood,
+        r#"
+        SELECT id, source, source_id, name, brand, barcode, calories, protein, fat, carbohydrates, fiber, sugar, sodium,
+        word_similarity(lower($1), lower(name)) as "score?"
+        FROM reference_foods
+        WHERE (lower($1) <% lower(name) OR name ILIKE '%' || $1 || '%')
+        AND ($2::text IS NULL OR source = $2)
+        ORDER BY 14 DESC, length(name) ASC
+        LIMIT $3
+        "#,
+        params.search.trim(),
+        params.source,
+        params.limit.unwrap_or(10).clamp(1, 100)
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to search reference foods: {}", e),
+        )
+    })?;
+
+    Ok(Json(ReferenceFoodsResponse { reference_foods }))
+}
+
+/// Suggests reference foods for an ingredient by how similar their names are to its name and aliases.
+/// Word similarity is used as dataset names are long, "Tomato" should still find "Tomatoes, red, raw".
+pub async fn get_reference_food_suggestions(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ReferenceFoodsResponse>, (StatusCode, String)> {
+    let reference_foods = sqlx::query_as!(
+        ReferenceFood,
+        r#"
+        WITH names AS (
+            SELECT lower(name) as name FROM ingredient_items WHERE id = $1
+            UNION
+            SELECT lower(name) FROM ingredient_aliases WHERE ingredient_item_id = $1
+        )
+        SELECT r.id, r.source, r.source_id, r.name, r.brand, r.barcode, r.calories, r.protein, r.fat, r.carbohydrates,
+        r.fiber, r.sugar, r.sodium, max(word_similarity(n.name, lower(r.name))) as "score?"
+        FROM reference_foods r
+        JOIN names n ON n.name <% lower(r.name)
+        GROUP BY r.id
+        ORDER BY 14 DESC, length(r.name) ASC
+        LIMIT 10
+        "#,
+        id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to suggest reference foods: {}", e),
+        )
+    })?;
+
+    Ok(Json(ReferenceFoodsResponse { reference_foods }))
+}
+
+pub async fn get_ingredient_reference_food(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ReferenceFood>, (StatusCode, String)> {
+    let reference_food = sqlx::query_as!(
+        ReferenceFood,
+        r#"
+        SELECT r.id, r.source, r.source_id, r.name, r.brand, r.barcode, r.calories, r.protein, r.fat, r.carbohydrates,
+        r.fiber, r.sugar, r.sodium, NULL::real as "score?"
+        FROM ingredient_items f
+        JOIN reference_foods r ON r.id = f.reference_food_id
+        WHERE f.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get reference food: {}", e),
+        )
+    })?
+    .ok_or((
+        StatusCode::NOT_FOUND,
+        "Ingredient item has no reference food".to_string(),
+    ))?;
+
+    Ok(Json(reference_food))
+}
+
+#[derive(Deserialize)]
+pub struct LinkReferenceFoodInformation {
+    reference_food_id: Uuid,
+}
+
+/// Links an ingredient to a reference food and takes over its nutrition facts.
+pub async fn link_reference_food(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(link): Json<LinkReferenceFoodInformation>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let reference_food = sqlx::query!(
+        "SELECT id FROM reference_foods WHERE id = $1",
+        link.reference_food_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get reference food: {}", e),
+        )
+    })?;
+    if reference_food.is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Reference food not found".to_string(),
+        ));
+    }
+
+    // Density and piece weight are not part of the datasets and are left as they are
+    let result = sqlx::query!(
+        r#"
+        UPDATE ingredient_items f SET
+            reference_food_id = r.id,
+            calories = r.calories,
+            protein = r.protein,
+            fat = r.fat,
+            carbohydrates = r.carbohydrates,
+            fiber = r.fiber,
+            sugar = r.sugar,
+            sodium = r.sodium
+        FROM reference_foods r
+        WHERE r.id = $1 AND f.id = $2 AND f.creator_id = $3
+        "#,
+        link.reference_food_id,
+        id,
+        user.id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to link reference food: {}", e),
+        )
+    })?;
+
+    if result.rows_affected() == 0 {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Ingredient item not found".to_string(),
+        ));
+    }
+
+    // Keto depends on the nutrition, so the labels of meals using it may change
+    let mut conn = state.db.acquire().await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to link reference food: {}", e),
+        )
+    })?;
+    refresh_ingredient_meal_diets(&mut conn, &[id])
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to update meal diets: {}", e),
+            )
+        })?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Removes the link but keeps the nutrition facts that were taken over.
+pub async fn unlink_reference_food(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let result = sqlx::query!(
+        "UPDATE ingredient_items SET reference_food_id = NULL WHERE id = $1 AND creator_id = $2",
+        id,
+        user.id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to unlink reference food: {}", e),
+        )
+    })?;
+
+    if result.rows_affected() == 0 {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Ingredient item not found".to_string(),
+        ));
+    }
+
+    Ok(StatusCode::OK)
+}
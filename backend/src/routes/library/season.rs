@@ -0,0 +1,211 @@
+use axum::extract::Path;
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use backend::util::{
+    app_state::AppState,
+    seasons::{
+        normalize_months, normalize_region, region_fallbacks, season_score, SeasonIngredient,
+        SeasonScore, DEFAULT_REGION,
+    },
+    user::User,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+/// The region the user cooks in, seasons fall back to the default region without one.
+pub async fn load_user_region(
+    db: impl PgExecutor<'_>,
+    user_id: Uuid,
+) -> Result<String, sqlx::Error> {
+    let region = sqlx::query_scalar!(
+        "SELECT region FROM user_regions WHERE user_id = $1",
+        user_id
+    )
+    .fetch_optional(db)
+    .await?;
+
+    Ok(region.unwrap_or_else(|| DEFAULT_REGION.to_string()))
+}
+
+/// Scores how in season a meal's ingredients are in a region and month.
+pub async fn load_meal_season(
+    db: impl PgExecutor<'_>,
+    meal_item_id: Uuid,
+    region: &str,
+    month: i32,
+) -> Result<SeasonScore, sqlx::Error> {
+    let ingredients = sqlx::query!(
+        r#"
+        SELECT DISTINCT f.name, (
+            SELECT s.months FROM ingredient_seasons s
+            WHERE s.ingredient_item_id = f.id AND s.region = ANY($2)
+            ORDER BY array_position($2, s.region::text)
+            LIMIT 1
+        ) as months
+        FROM expand_meal_item($1) e
+        JOIN meal_items m ON m.id = e.meal_item_id
+        JOIN ingredient_items f ON f.id = ANY(m.ingredient_items)
+        ORDER BY f.name ASC
+        "#,
+        meal_item_id,
+        &region_fallbacks(region)
+    )
+    .fetch_all(db)
+    .await?
+    .into_iter()
+    .map(|ingredient| SeasonIngredient {
+        name: ingredient.name,
+        months: ingredient.months,
+    })
+    .collect::<Vec<_>>();
+
+    Ok(season_score(&ingredients, month))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UserRegion {
+    region: String,
+}
+
+pub async fn get_user_region(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+) -> Result<Json<UserRegion>, (StatusCode, String)> {
+    let region = load_user_region(&state.db, user.id).await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get region: {}", e),
+        )
+    })?;
+
+    Ok(Json(UserRegion { region }))
+}
+
+/// Sets which region's seasons apply to the user, such as "uk" or "new-zealand".
+pub async fn update_user_region(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Json(region): Json<UserRegion>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    sqlx::query!(
+        r#"
+        INSERT INTO user_regions (user_id, region) VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE SET region = EXCLUDED.region
+        "#,
+        user.id,
+        normalize_region(&region.region)
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to update region: {}", e),
+        )
+    })?;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Serialize)]
+pub struct IngredientSeason {
+    pub region: String,
+    pub months: Vec<i32>,
+}
+#[derive(Serialize)]
+pub struct IngredientSeasonsResponse {
+    seasons: Vec<IngredientSeason>,
+}
+
+pub async fn get_ingredient_seasons(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<IngredientSeasonsResponse>, (StatusCode, String)> {
+    let seasons = sqlx::query_as!(
+        IngredientSeason,
+        "SELECT region, months FROM ingredient_seasons WHERE ingredient_item_id = $1 ORDER BY region ASC",
+        id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get ingredient seasons: {}", e),
+        )
+    })?;
+
+    Ok(Json(IngredientSeasonsResponse { seasons }))
+}
+
+#[derive(Deserialize)]
+pub struct IngredientSeasonInformation {
+    /// 1 for January to 12 for December
+    months: Vec<i32>,
+}
+
+/// Sets the months an ingredient is in season in a region, shared like aliases are.
+pub async fn update_ingredient_season(
+    State(state): State<AppState>,
+    Path((id, region)): Path<(Uuid, String)>,
+    Json(season): Json<IngredientSeasonInformation>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let months =
+        normalize_months(&season.months).map_err(|message| (StatusCode::BAD_REQUEST, message))?;
+
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO ingredient_seasons (ingredient_item_id, region, months)
+        SELECT id, $2, $3 FROM ingredient_items WHERE id = $1
+        ON CONFLICT (ingredient_item_id, region) DO UPDATE SET months = EXCLUDED.months
+        "#,
+        id,
+        normalize_region(&region),
+        &months
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to update ingredient season: {}", e),
+        )
+    })?;
+
+    if result.rows_affected() == 0 {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Ingredient item not found".to_string(),
+        ));
+    }
+
+    Ok(StatusCode::OK)
+}
+
+pub async fn delete_ingredient_season(
+    State(state): State<AppState>,
+    Path((id, region)): Path<(Uuid, String)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let result = sqlx::query!(
+        "DELETE FROM ingredient_seasons WHERE ingredient_item_id = $1 AND region = $2",
+        id,
+        normalize_region(&region)
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to delete ingredient season: {}", e),
+        )
+    })?;
+
+    if result.rows_affected() == 0 {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Ingredient season not found".to_string(),
+        ));
+    }
+
+    Ok(StatusCode::OK)
+}
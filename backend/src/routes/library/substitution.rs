@@ -0,0 +1,465 @@
+use axum::extract::Path;
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use backend::util::{
+    app_state::AppState,
+    substitutions::{common_substitutions, describe_substitution},
+    user::User,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::diet::refresh_meal_diets;
+use super::ingredient::{find_ingredient_by_name, find_or_create_ingredient};
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SubstitutionSource {
+    /// Added by a user for this ingredient
+    User,
+    /// One of the well known swaps
+    Common,
+    /// Another ingredient from the same category
+    Category,
+}
+
+#[derive(Serialize)]
+pub struct Substitution {
+    /// Set for user entries, which can be deleted by their creator
+    pub id: Option<Uuid>,
+    /// Missing when a common swap names an ingredient nobody has created yet
+    pub substitute_id: Option<Uuid>,
+    pub name: String,
+    pub ratio: f64,
+    pub note: Option<String>,
+    pub source: SubstitutionSource,
+}
+#[derive(Serialize)]
+pub struct SubstitutionsResponse {
+    substitutions: Vec<Substitution>,
+}
+
+/// Suggests what can replace an ingredient. User entries come first, then common swaps and
+/// finally ingredients sharing its category. Substitutes the user is allergic to are left out.
+pub async fn get_ingredient_substitutions(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<SubstitutionsResponse>, (StatusCode, String)> {
+    let error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get ingredient substitutions: {}", e),
+        )
+    };
+
+    let ingredient = sqlx::query!(
+        r#"
+        SELECT f.name, f.category_id, c.name as "category_name?"
+        FROM ingredient_items f
+        LEFT JOIN ingredient_categories c ON c.id = f.category_id
+        WHERE f.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(error)?
+    .ok_or((
+        StatusCode::NOT_FOUND,
+        "Ingredient item not found".to_string(),
+    ))?;
+
+    let mut substitutions: Vec<Substitution> = sqlx::query!(
+        r#"
+        SELECT s.id, s.substitute_id, f.name, s.ratio, s.note
+        FROM ingredient_substitutions s
+        JOIN ingredient_items f ON f.id = s.substitute_id
+        WHERE s.ingredient_item_id = $1
+        ORDER BY s.creation_date ASC
+        "#,
+        id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(error)?
+    .into_iter()
+    .map(|row| Substitution {
+        id: Some(row.id),
+        substitute_id: Some(row.substitute_id),
+        name: row.name,
+        ratio: row.ratio,
+        note: row.note,
+        source: SubstitutionSource::User,
+    })
+    .collect();
+
+    for common in common_substitutions(&ingredient.name) {
+        let found = find_ingredient_by_name(&state.db, common.to)
+            .await
+            .map_err(error)?;
+        let (substitute_id, name) = match found {
+            Some((substitute_id, name)) => (Some(substitute_id), name),
+            None => (None, common.to.to_string()),
+        };
+        substitutions.push(Substitution {
+            id: None,
+            substitute_id,
+            name,
+            ratio: common.ratio,
+            note: common.note.map(str::to_string),
+            source: SubstitutionSource::Common,
+        });
+    }
+
+    if let Some(category_id) = ingredient.category_id {
+        let siblings = sqlx::query!(
+            "SELECT id, name FROM ingredient_items WHERE category_id = $1 AND id <> $2 ORDER BY name ASC LIMIT 5",
+            category_id,
+            id
+        )
+        .fetch_all(&state.db)
+        .await
+        .map_err(error)?;
+        substitutions.extend(siblings.into_iter().map(|sibling| {
+            Substitution {
+                id: None,
+                substitute_id: Some(sibling.id),
+                name: sibling.name,
+                ratio: 1.0,
+                note: ingredient
+                    .category_name
+                    .as_ref()
+                    .map(|category| format!("also in {}", category)),
+                source: SubstitutionSource::Category,
+            }
+        }));
+    }
+
+    let substitute_ids: Vec<Uuid> = substitutions
+        .iter()
+        .filter_map(|substitution| substitution.substitute_id)
+        .collect();
+    let avoided = sqlx::query_scalar!(
+        r#"
+        SELECT DISTINCT f.id
+        FROM ingredient_items f
+        JOIN user_allergies u ON u.allergen = ANY(f.allergens)
+        WHERE u.user_id = $1 AND f.id = ANY($2)
+        "#,
+        user.id,
+        &substitute_ids
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(error)?;
+
+    // The first suggestion for a substitute wins, later sources only repeat it
+    let mut seen = Vec::new();
+    substitutions.retain(|substitution| {
+        let key = substitution
+            .substitute_id
+            .map(|id| id.to_string())
+            .unwrap_or_else(|| substitution.name.to_lowercase());
+        let keep = substitution.substitute_id != Some(id)
+            && !substitution
+                .substitute_id
+                .is_some_and(|substitute_id| avoided.contains(&substitute_id))
+            && !seen.contains(&key);
+        seen.push(key);
+        keep
+    });
+
+    Ok(Json(SubstitutionsResponse { substitutions }))
+}
+
+#[derive(Deserialize)]
+pub struct SubstitutionCreationInformation {
+    substitute_id: Uuid,
+    ratio: Option<f64>,
+    /// Context for the swap, such as "baking only"
+    note: Option<String>,
+}
+#[derive(Serialize)]
+pub struct SubstitutionCreationResponse {
+    id: Uuid,
+}
+
+pub async fn create_ingredient_substitution(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(substitution): Json<SubstitutionCreationInformation>,
+) -> Result<Json<SubstitutionCreationResponse>, (StatusCode, String)> {
+    if substitution.substitute_id == id {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "An ingredient cannot substitute itself".to_string(),
+        ));
+    }
+    if substitution.ratio.is_some_and(|ratio| ratio <= 0.0) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "The ratio must be greater than zero".to_string(),
+        ));
+    }
+
+    let created = sqlx::query!(
+        r#"
+        INSERT INTO ingredient_substitutions (ingredient_item_id, substitute_id, ratio, note, creator_id)
+        VALUES ($1, $2, COALESCE($3::float8, 1), $4, $5)
+        ON CONFLICT (ingredient_item_id, substitute_id) DO NOTHING
+        RETURNING id
+        "#,
+        id,
+        substitution.substitute_id,
+        substitution.ratio,
+        substitution
+            .note
+            .as_deref()
+            .map(str::trim)
+            .filter(|note| !note.is_empty()),
+        user.id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| match e.as_database_error() {
+        Some(error) if error.is_foreign_key_violation() => (
+            StatusCode::NOT_FOUND,
+            "Ingredient item not found".to_string(),
+        ),
+        _ => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to create ingredient substitution: {}", e),
+        ),
+    })?
+    .ok_or((
+        StatusCode::CONFLICT,
+        "This substitution already exists".to_string(),
+    ))?;
+
+    Ok(Json(SubstitutionCreationResponse { id: created.id }))
+}
+
+pub async fn delete_ingredient_substitution(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let result = sqlx::query!(
+        "DELETE FROM ingredient_substitutions WHERE id = $1 AND creator_id = $2",
+        id,
+        user.id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to delete ingredient substitution: {}", e),
+        )
+    })?;
+
+    if result.rows_affected() == 0 {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Ingredient substitution not found".to_string(),
+        ));
+    }
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct MealSubstitutionInformation {
+    ingredient_id: Uuid,
+    substitute_id: Option<Uuid>,
+    /// Used when the substitute is not an ingredient yet, as with some common swaps
+    substitute_name: Option<String>,
+    /// Defaults to the stored or common ratio for this swap, otherwise 1
+    ratio: Option<f64>,
+}
+#[derive(Serialize)]
+pub struct MealSubstitutionResponse {
+    id: Uuid,
+}
+
+/// Produces a variant of a meal with one ingredient swapped, saved as a fork of the original.
+pub async fn substitute_meal_ingredient(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(substitution): Json<MealSubstitutionInformation>,
+) -> Result<Json<MealSubstitutionResponse>, (StatusCode, String)> {
+    let error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to substitute meal ingredient: {}", e),
+        )
+    };
+    let mut tx = state.db.begin().await.map_err(error)?;
+
+    let meal = sqlx::query!(
+        "SELECT name, ingredient_items FROM meal_items WHERE id = $1",
+        id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(error)?
+    .ok_or((StatusCode::NOT_FOUND, "Meal item not found".to_string()))?;
+    if !meal.ingredient_items.contains(&substitution.ingredient_id) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "The meal does not use this ingredient".to_string(),
+        ));
+    }
+
+    let substitute_id = match (
+        substitution.substitute_id,
+        substitution.substitute_name.as_deref(),
+    ) {
+        (Some(substitute_id), _) => substitute_id,
+        (None, Some(name)) if !name.trim().is_empty() => {
+            find_or_create_ingredient(&mut tx, name.trim(), user.id)
+                .await
+                .map_err(error)?
+        }
+        _ => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Either substitute_id or substitute_name is required".to_string(),
+            ))
+        }
+    };
+    if substitute_id == substitution.ingredient_id {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "An ingredient cannot substitute itself".to_string(),
+        ));
+    }
+
+    let names = sqlx::query!(
+        "SELECT id, name FROM ingredient_items WHERE id = ANY($1)",
+        &[substitution.ingredient_id, substitute_id]
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(error)?;
+    let name_of = |ingredient_id: Uuid| {
+        names
+            .iter()
+            .find(|row| row.id == ingredient_id)
+            .map(|row| row.name.clone())
+    };
+    let (Some(original_name), Some(substitute_name)) =
+        (name_of(substitution.ingredient_id), name_of(substitute_id))
+    else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Ingredient item not found".to_string(),
+        ));
+    };
+
+    let stored = sqlx::query!(
+        "SELECT ratio, note FROM ingredient_substitutions WHERE ingredient_item_id = $1 AND substitute_id = $2",
+        substitution.ingredient_id,
+        substitute_id
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(error)?;
+    let common = common_substitutions(&original_name).find(|common| {
+        common.to.eq_ignore_ascii_case(&substitute_name)
+            || substitution
+                .substitute_name
+                .as_deref()
+                .is_some_and(|name| common.to.eq_ignore_ascii_case(name.trim()))
+    });
+    let ratio = substitution
+        .ratio
+        .or(stored.as_ref().map(|stored| stored.ratio))
+        .or(common.map(|common| common.ratio))
+        .unwrap_or(1.0);
+    if ratio <= 0.0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "The ratio must be greater than zero".to_string(),
+        ));
+    }
+    let note = stored
+        .and_then(|stored| stored.note)
+        .or(common.and_then(|common| common.note.map(str::to_string)));
+
+    // The substitute takes the original's place, unless the meal already uses it
+    let mut ingredient_items: Vec<Uuid> = Vec::with_capacity(meal.ingredient_items.len());
+    for ingredient_id in &meal.ingredient_items {
+        let ingredient_id = if *ingredient_id == substitution.ingredient_id {
+            substitute_id
+        } else {
+            *ingredient_id
+        };
+        if !ingredient_items.contains(&ingredient_id) {
+            ingredient_items.push(ingredient_id);
+        }
+    }
+
+    let description =
+        describe_substitution(&original_name, &substitute_name, ratio, note.as_deref());
+    let variant = sqlx::query!(
+        r#"
+        INSERT INTO meal_items (name, ingredient_items, instructions, servings, prep_time, cook_time, total_time, difficulty, equipment, cuisine, course, tags, creator_id, forked_from, forked_from_creator_id, fork_sync_date)
+        SELECT $2, $3, instructions || E'\n\n' || $4, servings, prep_time, cook_time, total_time, difficulty, equipment, cuisine, course, tags, $5, id, creator_id, update_date
+        FROM meal_items
+        WHERE id = $1
+        RETURNING id
+        "#,
+        id,
+        format!("{} with {}", meal.name, substitute_name),
+        &ingredient_items,
+        format!("Substitution: {}", description),
+        user.id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(error)?;
+
+    // Amounts carry over, the swapped one scaled by the ratio in the same unit
+    sqlx::query!(
+        r#"
+        INSERT INTO meal_item_amounts (meal_item_id, ingredient_item_id, quantity, unit)
+        SELECT $1,
+            CASE WHEN ingredient_item_id = $3 THEN $4 ELSE ingredient_item_id END,
+            CASE WHEN ingredient_item_id = $3 THEN quantity * $5 ELSE quantity END,
+            unit
+        FROM meal_item_amounts
+        WHERE meal_item_id = $2
+        ON CONFLICT (meal_item_id, ingredient_item_id) DO NOTHING
+        "#,
+        variant.id,
+        id,
+        substitution.ingredient_id,
+        substitute_id,
+        ratio
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO meal_item_components (meal_item_id, component_id, quantity, position)
+        SELECT $1, component_id, quantity, position FROM meal_item_components WHERE meal_item_id = $2
+        "#,
+        variant.id,
+        id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
+
+    refresh_meal_diets(&mut tx, &[variant.id])
+        .await
+        .map_err(error)?;
+
+    tx.commit().await.map_err(error)?;
+    Ok(Json(MealSubstitutionResponse { id: variant.id }))
+}
@@ -0,0 +1,182 @@
+use axum::extract::Path;
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use backend::util::{
+    app_state::AppState,
+    tags::{normalize_tag, normalize_tags, Course, Cuisine, COURSES, CUISINES},
+    user::User,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+#[derive(Serialize)]
+pub struct TaxonomyResponse {
+    cuisines: &'static [Cuisine],
+    courses: &'static [Course],
+}
+
+pub async fn get_taxonomy() -> Json<TaxonomyResponse> {
+    Json(TaxonomyResponse {
+        cuisines: CUISINES,
+        courses: COURSES,
+    })
+}
+
+/// Replaces any of `tags` on the user's meals with `into`, leaving each meal's tags sorted and unique.
+async fn replace_tags(
+    db: impl PgExecutor<'_>,
+    user_id: Uuid,
+    tags: &[String],
+    into: &str,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE meal_items SET
+            tags = ARRAY(
+                SELECT DISTINCT CASE WHEN t = ANY($2) THEN $3 ELSE t END
+                FROM unnest(tags) t
+                ORDER BY 1
+            ),
+            update_date = NOW()
+        WHERE creator_id = $1 AND tags && $2::varchar[]
+        "#,
+        user_id,
+        tags,
+        into
+    )
+    .execute(db)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+#[derive(Serialize)]
+pub struct Tag {
+    pub name: String,
+    pub count: i64,
+}
+#[derive(Serialize)]
+pub struct TagsResponse {
+    tags: Vec<Tag>,
+}
+
+/// Lists the tags on the user's meals with how many meals use each.
+pub async fn get_tags(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+) -> Result<Json<TagsResponse>, (StatusCode, String)> {
+    let tags = sqlx::query_as!(
+        Tag,
+        r#"
+        SELECT t.tag as "name!", count(*) as "count!"
+        FROM meal_items m
+        CROSS JOIN LATERAL unnest(m.tags) t(tag)
+        WHERE m.creator_id = $1
+        GROUP BY t.tag
+        ORDER BY t.tag ASC
+        "#,
+        user.id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get tags: {}", e),
+        )
+    })?;
+
+    Ok(Json(TagsResponse { tags }))
+}
+
+fn parse_tag(tag: &str) -> Result<String, (StatusCode, String)> {
+    normalize_tag(tag).ok_or((
+        StatusCode::BAD_REQUEST,
+        "The tag cannot be empty".to_string(),
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct TagRenameInformation {
+    name: String,
+}
+
+/// Renames a tag on all of the user's meals, renaming onto a tag in use is a merge instead.
+pub async fn rename_tag(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(tag): Path<String>,
+    Json(rename): Json<TagRenameInformation>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let tag = parse_tag(&tag)?;
+    let name = parse_tag(&rename.name)?;
+    let error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to rename tag: {}", e),
+        )
+    };
+    let mut tx = state.db.begin().await.map_err(error)?;
+
+    if name != tag {
+        let taken = sqlx::query_scalar!(
+            r#"SELECT EXISTS (SELECT 1 FROM meal_items WHERE creator_id = $1 AND $2 = ANY(tags)) as "taken!""#,
+            user.id,
+            name
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(error)?;
+        if taken {
+            return Err((
+                StatusCode::CONFLICT,
+                format!("Tag \"{}\" already exists, merge the tags instead", name),
+            ));
+        }
+    }
+
+    let renamed = replace_tags(&mut *tx, user.id, &[tag], &name)
+        .await
+        .map_err(error)?;
+    if renamed == 0 {
+        return Err((StatusCode::NOT_FOUND, "Tag not found".to_string()));
+    }
+
+    tx.commit().await.map_err(error)?;
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct TagMergeInformation {
+    tags: Vec<String>,
+    /// The tag the others become, it may be new or one of `tags`
+    into: String,
+}
+#[derive(Serialize)]
+pub struct TagMergeResponse {
+    meal_items: u64,
+}
+
+/// Merges tags on all of the user's meals, such as "quick" and "weeknight" into "30 minutes".
+pub async fn merge_tags(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Json(merge): Json<TagMergeInformation>,
+) -> Result<Json<TagMergeResponse>, (StatusCode, String)> {
+    let into = parse_tag(&merge.into)?;
+    let tags = normalize_tags(&merge.tags);
+    if tags.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "No tags to merge".to_string()));
+    }
+
+    let meal_items = replace_tags(&state.db, user.id, &tags, &into)
+        .await
+        .map_err(|e| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to merge tags: {}", e),
+            )
+        })?;
+
+    Ok(Json(TagMergeResponse { meal_items }))
+}
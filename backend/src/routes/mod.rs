@@ -1,3 +1,6 @@
 pub mod auth;
 pub mod calendar;
 pub mod library;
+pub mod pantry;
+pub mod shopping_list;
+pub mod store;
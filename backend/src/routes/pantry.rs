@@ -0,0 +1,296 @@
+use axum::extract::{Path, Query};
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use backend::util::{app_state::AppState, units::normalize_unit, user::User};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::library::{barcode::parse_barcode, meal::FoodItem};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PantryLocation {
+    Fridge,
+    Freezer,
+    Cupboard,
+}
+
+impl PantryLocation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PantryLocation::Fridge => "fridge",
+            PantryLocation::Freezer => "freezer",
+            PantryLocation::Cupboard => "cupboard",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PantryItemCreationInformation {
+    ingredient_item_id: Uuid,
+    quantity: f64,
+    unit: Option<String>,
+    location: PantryLocation,
+    best_before: Option<NaiveDate>,
+    /// The scanned product, kept so the same package can be found again
+    barcode: Option<String>,
+}
+
+pub async fn create_pantry_item(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Json(pantry_item): Json<PantryItemCreationInformation>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let barcode = pantry_item
+        .barcode
+        .as_deref()
+        .map(parse_barcode)
+        .transpose()?;
+
+    sqlx::query!(
+        "INSERT INTO pantry_items (user_id, ingredient_item_id, quantity, unit, location, best_before, barcode) VALUES ($1, $2, $3, $4, $5, $6, $7)",
+        user.id,
+        pantry_item.ingredient_item_id,
+        pantry_item.quantity,
+        normalize_unit(pantry_item.unit.as_deref()),
+        pantry_item.location.as_str(),
+        pantry_item.best_before,
+        barcode
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create pantry item: {}", e)))?;
+    Ok(StatusCode::OK)
+}
+
+#[derive(Serialize)]
+pub struct PantryItem {
+    pub id: Uuid,
+    pub ingredient_item_id: Uuid,
+    pub name: String,
+    pub quantity: f64,
+    pub unit: Option<String>,
+    pub location: String,
+    pub best_before: Option<NaiveDate>,
+    pub barcode: Option<String>,
+    pub creation_date: DateTime<Utc>,
+}
+#[derive(Serialize)]
+pub struct PantryItemsResponse {
+    pantry_items: Vec<PantryItem>,
+}
+#[derive(Deserialize)]
+pub struct GetPantryItemsInformation {
+    location: Option<PantryLocation>,
+}
+
+pub async fn get_pantry_items(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Query(params): Query<GetPantryItemsInformation>,
+) -> Result<Json<PantryItemsResponse>, (StatusCode, String)> {
+    let pantry_items = sqlx::query_as!(
+        PantryItem,
+        r#"
+        SELECT p.id, p.ingredient_item_id, f.name, p.quantity, p.unit, p.location, p.best_before, p.barcode, p.creation_date
+        FROM pantry_items p
+        JOIN ingredient_items f ON f.id = p.ingredient_item_id
+        WHERE p.user_id = $1 AND ($2::text IS NULL OR p.location = $2)
+        ORDER BY p.best_before ASC NULLS LAST, f.name ASC
+        "#,
+        user.id,
+        params.location.map(|location| location.as_str())
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get pantry items: {}", e),
+        )
+    })?;
+
+    Ok(Json(PantryItemsResponse { pantry_items }))
+}
+
+#[derive(Deserialize)]
+pub struct PantryItemUpdateInformation {
+    quantity: Option<f64>,
+    unit: Option<String>,
+    location: Option<PantryLocation>,
+    best_before: Option<NaiveDate>,
+    barcode: Option<String>,
+}
+
+pub async fn update_pantry_item(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(pantry_item): Json<PantryItemUpdateInformation>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let barcode = pantry_item
+        .barcode
+        .as_deref()
+        .map(parse_barcode)
+        .transpose()?;
+
+    let result = sqlx::query!(
+        r#"
+        UPDATE pantry_items SET
+            quantity = COALESCE($1, quantity),
+            unit = COALESCE($2, unit),
+            location = COALESCE($3, location),
+            best_before = COALESCE($4, best_before),
+            barcode = COALESCE($7, barcode)
+        WHERE id = $5 AND user_id = $6
+        "#,
+        pantry_item.quantity,
+        normalize_unit(pantry_item.unit.as_deref()),
+        pantry_item.location.map(|location| location.as_str()),
+        pantry_item.best_before,
+        id,
+        user.id,
+        barcode
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to update pantry item: {}", e),
+        )
+    })?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Pantry item not found".to_string()));
+    }
+
+    Ok(StatusCode::OK)
+}
+
+pub async fn delete_pantry_item(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    sqlx::query!(
+        "DELETE FROM pantry_items WHERE id = $1 AND user_id = $2",
+        id,
+        user.id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to delete pantry item: {}", e),
+        )
+    })?;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct GetUseSoonInformation {
+    start: Option<i64>,
+    end: Option<i64>,
+    days: Option<i32>,
+}
+#[derive(Serialize)]
+pub struct UseSoonMealItem {
+    pub id: Uuid,
+    pub name: String,
+    pub creator_id: Uuid,
+    pub best_before: NaiveDate,
+    pub expiring: Vec<FoodItem>,
+}
+#[derive(Serialize)]
+pub struct UseSoonResponse {
+    pantry_items: Vec<PantryItem>,
+    meal_items: Vec<UseSoonMealItem>,
+}
+
+pub async fn get_use_soon(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Query(params): Query<GetUseSoonInformation>,
+) -> Result<Json<UseSoonResponse>, (StatusCode, String)> {
+    let start = params.start.unwrap_or(0);
+    let end = params.end.unwrap_or(10);
+    let days = params.days.unwrap_or(3);
+
+    let pantry_items = sqlx::query_as!(
+        PantryItem,
+        r#"
+        SELECT p.id, p.ingredient_item_id, f.name, p.quantity, p.unit, p.location, p.best_before, p.barcode, p.creation_date
+        FROM pantry_items p
+        JOIN ingredient_items f ON f.id = p.ingredient_item_id
+        WHERE p.user_id = $1 AND p.best_before <= CURRENT_DATE + $2::int
+        ORDER BY p.best_before ASC, f.name ASC
+        "#,
+        user.id,
+        days
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get pantry items: {}", e),
+        )
+    })?;
+
+    // Meals using the most expiring ingredients, sub-recipes included, come first, ties go to the soonest expiry.
+    // Meals the user is allergic to are left out like everywhere else meals are suggested.
+    let query = sqlx::query!(
+        r#"
+        SELECT m.id, m.name, m.creator_id,
+        min(e.best_before) as "best_before!",
+        json_agg(json_build_object('id', f.id, 'name', f.name) ORDER BY e.best_before, f.name) as "expiring!"
+        FROM meal_items m
+        JOIN (
+            SELECT ingredient_item_id, min(best_before) as best_before
+            FROM pantry_items
+            WHERE user_id = $1 AND best_before <= CURRENT_DATE + $2::int
+            GROUP BY ingredient_item_id
+        ) e ON e.ingredient_item_id = ANY(m.expanded_ingredient_items)
+        JOIN ingredient_items f ON f.id = e.ingredient_item_id
+        WHERE NOT EXISTS (
+            SELECT 1 FROM ingredient_items x
+            JOIN user_allergies u ON u.allergen = ANY(x.allergens)
+            WHERE x.id = ANY(m.expanded_ingredient_items) AND u.user_id = $1
+        )
+        GROUP BY m.id, m.name, m.creator_id
+        ORDER BY count(*) DESC, min(e.best_before) ASC, m.name ASC
+        OFFSET $3 LIMIT $4
+        "#,
+        user.id,
+        days,
+        start,
+        end
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get meal items: {}", e),
+        )
+    })?;
+
+    let meal_items = query
+        .into_iter()
+        .map(|meal| UseSoonMealItem {
+            id: meal.id,
+            name: meal.name,
+            creator_id: meal.creator_id,
+            best_before: meal.best_before,
+            expiring: serde_json::from_value(meal.expiring).unwrap(),
+        })
+        .collect();
+
+    Ok(Json(UseSoonResponse {
+        pantry_items,
+        meal_items,
+    }))
+}
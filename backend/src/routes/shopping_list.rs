@@ -0,0 +1,549 @@
+use axum::extract::{Path, Query};
+use axum::http::header::{HeaderName, CONTENT_DISPOSITION, CONTENT_TYPE};
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use backend::util::{
+    app_state::AppState,
+    shopping_list::{
+        build_shopping_list, export_shopping_list, IngredientAmount, ShoppingListFormat,
+        ShoppingListLine,
+    },
+    store_sections::{guess_section, section_order, section_position},
+    units::normalize_unit,
+    user::User,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct ShoppingListCreationInformation {
+    name: Option<String>,
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    #[serde(default)]
+    shared_with: Vec<Uuid>,
+}
+#[derive(Serialize)]
+pub struct ShoppingListCreationResponse {
+    id: Uuid,
+}
+
+pub async fn create_shopping_list(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Json(shopping_list): Json<ShoppingListCreationInformation>,
+) -> Result<Json<ShoppingListCreationResponse>, (StatusCode, String)> {
+    let error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to create shopping list: {}", e),
+        )
+    };
+
+    // Every planned meal counts, a meal planned twice is bought for twice.
+    // Sub-recipes are expanded so the dough's flour lands on the list too.
+    let needed = sqlx::query_as!(
+        IngredientAmount,
+        r#"
+        SELECT f.id as ingredient_item_id, f.name, a.quantity * e.scale as "quantity?", a.unit
+        FROM calendar_items c
+        CROSS JOIN LATERAL expand_meal_item(c.meal_item_id) e
+        JOIN meal_items m ON m.id = e.meal_item_id
+        JOIN ingredient_items f ON f.id = ANY(m.ingredient_items)
+        LEFT JOIN meal_item_amounts a ON a.meal_item_id = m.id AND a.ingredient_item_id = f.id
+        WHERE (c.user_id = $1 OR $1 = ANY(c.shared_with))
+        AND c.start_date >= $2 AND c.start_date < $3
+        AND c.cooked_date IS NULL
+        "#,
+        user.id,
+        shopping_list.start_date,
+        shopping_list.end_date
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(error)?;
+
+    let pantry = sqlx::query_as!(
+        IngredientAmount,
+        r#"
+        SELECT p.ingredient_item_id, f.name, p.quantity as "quantity?", p.unit
+        FROM pantry_items p
+        JOIN ingredient_items f ON f.id = p.ingredient_item_id
+        WHERE p.user_id = $1
+        "#,
+        user.id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(error)?;
+
+    let name = shopping_list.name.unwrap_or_else(|| {
+        format!(
+            "Shopping {} to {}",
+            shopping_list.start_date.format("%Y-%m-%d"),
+            shopping_list.end_date.format("%Y-%m-%d")
+        )
+    });
+
+    let mut tx = state.db.begin().await.map_err(error)?;
+
+    let list = sqlx::query!(
+        "INSERT INTO shopping_lists (user_id, shared_with, name, start_date, end_date) VALUES ($1, $2, $3, $4, $5) RETURNING id",
+        user.id,
+        &shopping_list.shared_with,
+        name,
+        shopping_list.start_date,
+        shopping_list.end_date
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(error)?;
+
+    for item in build_shopping_list(&needed, &pantry) {
+        sqlx::query!(
+            "INSERT INTO shopping_list_items (shopping_list_id, ingredient_item_id, name, quantity, unit) VALUES ($1, $2, $3, $4, $5)",
+            list.id,
+            item.ingredient_item_id,
+            item.name,
+            item.quantity,
+            item.unit
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(error)?;
+    }
+
+    tx.commit().await.map_err(error)?;
+    Ok(Json(ShoppingListCreationResponse { id: list.id }))
+}
+
+#[derive(Serialize)]
+pub struct ShoppingListSummary {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub shared_with: Vec<Uuid>,
+    pub name: String,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    pub creation_date: DateTime<Utc>,
+    pub items: i64,
+    pub checked: i64,
+}
+#[derive(Serialize)]
+pub struct ShoppingListsResponse {
+    shopping_lists: Vec<ShoppingListSummary>,
+}
+
+pub async fn get_shopping_lists(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+) -> Result<Json<ShoppingListsResponse>, (StatusCode, String)> {
+    let shopping_lists = sqlx::query_as!(
+        ShoppingListSummary,
+        r#"
+        SELECT l.id, l.user_id, l.shared_with, l.name, l.start_date, l.end_date, l.creation_date,
+        count(i.id) as "items!",
+        count(i.id) FILTER (WHERE i.checked) as "checked!"
+        FROM shopping_lists l
+        LEFT JOIN shopping_list_items i ON i.shopping_list_id = l.id
+        WHERE l.user_id = $1 OR $1 = ANY(l.shared_with)
+        GROUP BY l.id
+        ORDER BY l.creation_date DESC
+        "#,
+        user.id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get shopping lists: {}", e),
+        )
+    })?;
+
+    Ok(Json(ShoppingListsResponse { shopping_lists }))
+}
+
+#[derive(Serialize)]
+pub struct ShoppingListItem {
+    pub id: Uuid,
+    pub ingredient_item_id: Option<Uuid>,
+    pub name: String,
+    pub quantity: Option<f64>,
+    pub unit: Option<String>,
+    pub section: String,
+    pub checked: bool,
+    pub checked_by: Option<Uuid>,
+}
+#[derive(Serialize)]
+pub struct ShoppingList {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub shared_with: Vec<Uuid>,
+    pub name: String,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    pub creation_date: DateTime<Utc>,
+    /// Sorted by section in the walking order of the chosen store
+    pub items: Vec<ShoppingListItem>,
+}
+#[derive(Deserialize)]
+pub struct GetShoppingListInformation {
+    store: Option<Uuid>,
+}
+
+async fn load_shopping_list(
+    state: &AppState,
+    id: Uuid,
+    user_id: Uuid,
+    store: Option<Uuid>,
+) -> Result<ShoppingList, (StatusCode, String)> {
+    let error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get shopping list: {}", e),
+        )
+    };
+
+    let list = sqlx::query!(
+        "SELECT id, user_id, shared_with, name, start_date, end_date, creation_date FROM shopping_lists WHERE id = $1 AND (user_id = $2 OR $2 = ANY(shared_with))",
+        id,
+        user_id
+    )
+    .fetch_optional(&state.db)
+    .await
+    .map_err(error)?
+    .ok_or((StatusCode::NOT_FOUND, "Shopping list not found".to_string()))?;
+
+    let custom_order = sqlx::query!(
+        r#"
+        SELECT a.section
+        FROM store_sections a
+        JOIN stores s ON s.id = a.store_id
+        WHERE s.id = $1 AND s.user_id = $2
+        ORDER BY a.position ASC
+        "#,
+        store,
+        user_id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(error)?;
+    let order = section_order(
+        &custom_order
+            .into_iter()
+            .map(|row| row.section)
+            .collect::<Vec<_>>(),
+    );
+
+    // The nearest category with a section decides when the ingredient has none of its own
+    let query = sqlx::query!(
+        r#"
+        SELECT i.id, i.ingredient_item_id, i.name, i.quantity, i.unit, i.checked, i.checked_by,
+        COALESCE(f.store_section, (
+            SELECT c.store_section
+            FROM ingredient_category_ancestors a
+            JOIN ingredient_categories c ON c.id = a.ancestor_id
+            WHERE a.category_id = f.category_id AND c.store_section IS NOT NULL
+            ORDER BY a.depth ASC
+            LIMIT 1
+        )) as "store_section?"
+        FROM shopping_list_items i
+        LEFT JOIN ingredient_items f ON f.id = i.ingredient_item_id
+        WHERE i.shopping_list_id = $1
+        "#,
+        id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(error)?;
+
+    let mut items: Vec<ShoppingListItem> = query
+        .into_iter()
+        .map(|item| ShoppingListItem {
+            section: item
+                .store_section
+                .unwrap_or_else(|| guess_section(&item.name).to_string()),
+            id: item.id,
+            ingredient_item_id: item.ingredient_item_id,
+            name: item.name,
+            quantity: item.quantity,
+            unit: item.unit,
+            checked: item.checked,
+            checked_by: item.checked_by,
+        })
+        .collect();
+    items.sort_by_key(|item| {
+        (
+            section_position(&order, &item.section),
+            item.checked,
+            item.name.to_lowercase(),
+        )
+    });
+
+    Ok(ShoppingList {
+        id: list.id,
+        user_id: list.user_id,
+        shared_with: list.shared_with,
+        name: list.name,
+        start_date: list.start_date,
+        end_date: list.end_date,
+        creation_date: list.creation_date,
+        items,
+    })
+}
+
+pub async fn get_shopping_list(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<GetShoppingListInformation>,
+) -> Result<Json<ShoppingList>, (StatusCode, String)> {
+    let list = load_shopping_list(&state, id, user.id, params.store).await?;
+    Ok(Json(list))
+}
+
+#[derive(Deserialize)]
+pub struct ExportShoppingListInformation {
+    format: ShoppingListFormat,
+    store: Option<Uuid>,
+}
+
+pub async fn export_shopping_list_items(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<ExportShoppingListInformation>,
+) -> Result<([(HeaderName, String); 2], String), (StatusCode, String)> {
+    let list = load_shopping_list(&state, id, user.id, params.store).await?;
+
+    // Items are already in walking order, so each section is one consecutive run
+    let mut sections: Vec<(String, Vec<ShoppingListLine>)> = Vec::new();
+    for item in list.items {
+        let line = ShoppingListLine {
+            name: item.name,
+            quantity: item.quantity,
+            unit: item.unit,
+            checked: item.checked,
+        };
+        match sections.last_mut() {
+            Some((section, lines)) if *section == item.section => lines.push(line),
+            _ => sections.push((item.section, vec![line])),
+        }
+    }
+
+    let file_name: String = list
+        .name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+
+    Ok((
+        [
+            (CONTENT_TYPE, params.format.content_type().to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!(
+                    "attachment; filename=\"{}.{}\"",
+                    file_name,
+                    params.format.extension()
+                ),
+            ),
+        ],
+        export_shopping_list(params.format, &list.name, &sections),
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct ShoppingListUpdateInformation {
+    name: Option<String>,
+    shared_with: Option<Vec<Uuid>>,
+}
+
+pub async fn update_shopping_list(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(shopping_list): Json<ShoppingListUpdateInformation>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    // Only the owner decides who the list is shared with
+    let result = sqlx::query!(
+        r#"
+        UPDATE shopping_lists SET
+            name = COALESCE($1, name),
+            shared_with = COALESCE($2, shared_with)
+        WHERE id = $3 AND user_id = $4
+        "#,
+        shopping_list.name,
+        shopping_list.shared_with.as_deref(),
+        id,
+        user.id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to update shopping list: {}", e),
+        )
+    })?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Shopping list not found".to_string()));
+    }
+
+    Ok(StatusCode::OK)
+}
+
+pub async fn delete_shopping_list(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    sqlx::query!(
+        "DELETE FROM shopping_lists WHERE id = $1 AND user_id = $2",
+        id,
+        user.id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to delete shopping list: {}", e),
+        )
+    })?;
+
+    Ok(StatusCode::OK)
+}
+
+async fn check_shopping_list_access(
+    db: impl PgExecutor<'_>,
+    id: Uuid,
+    user_id: Uuid,
+) -> Result<(), (StatusCode, String)> {
+    sqlx::query!(
+        "SELECT id FROM shopping_lists WHERE id = $1 AND (user_id = $2 OR $2 = ANY(shared_with))",
+        id,
+        user_id
+    )
+    .fetch_optional(db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get shopping list: {}", e),
+        )
+    })?
+    .ok_or((StatusCode::NOT_FOUND, "Shopping list not found".to_string()))?;
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct ShoppingListItemCreationInformation {
+    ingredient_item_id: Option<Uuid>,
+    name: String,
+    quantity: Option<f64>,
+    unit: Option<String>,
+}
+
+pub async fn create_shopping_list_item(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(item): Json<ShoppingListItemCreationInformation>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    check_shopping_list_access(&state.db, id, user.id).await?;
+
+    sqlx::query!(
+        "INSERT INTO shopping_list_items (shopping_list_id, ingredient_item_id, name, quantity, unit) VALUES ($1, $2, $3, $4, $5)",
+        id,
+        item.ingredient_item_id,
+        item.name,
+        item.quantity,
+        normalize_unit(item.unit.as_deref())
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to create shopping list item: {}", e)))?;
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct ShoppingListItemUpdateInformation {
+    name: Option<String>,
+    quantity: Option<f64>,
+    unit: Option<String>,
+    checked: Option<bool>,
+}
+
+pub async fn update_shopping_list_item(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path((id, item_id)): Path<(Uuid, Uuid)>,
+    Json(item): Json<ShoppingListItemUpdateInformation>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    check_shopping_list_access(&state.db, id, user.id).await?;
+
+    let result = sqlx::query!(
+        r#"
+        UPDATE shopping_list_items SET
+            name = COALESCE($1, name),
+            quantity = COALESCE($2, quantity),
+            unit = COALESCE($3, unit),
+            checked = COALESCE($4, checked),
+            checked_by = CASE WHEN $4 IS NULL THEN checked_by WHEN $4 THEN $7 END
+        WHERE id = $5 AND shopping_list_id = $6
+        "#,
+        item.name,
+        item.quantity,
+        normalize_unit(item.unit.as_deref()),
+        item.checked,
+        item_id,
+        id,
+        user.id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to update shopping list item: {}", e),
+        )
+    })?;
+
+    if result.rows_affected() == 0 {
+        return Err((
+            StatusCode::NOT_FOUND,
+            "Shopping list item not found".to_string(),
+        ));
+    }
+
+    Ok(StatusCode::OK)
+}
+
+pub async fn delete_shopping_list_item(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path((id, item_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    check_shopping_list_access(&state.db, id, user.id).await?;
+
+    sqlx::query!(
+        "DELETE FROM shopping_list_items WHERE id = $1 AND shopping_list_id = $2",
+        item_id,
+        id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to delete shopping list item: {}", e),
+        )
+    })?;
+
+    Ok(StatusCode::OK)
+}
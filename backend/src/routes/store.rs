@@ -0,0 +1,187 @@
+use axum::extract::Path;
+use axum::{extract::State, http::StatusCode, Extension, Json};
+use backend::util::{app_state::AppState, store_sections::normalize_section, user::User};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+/// Replaces the aisle order of a store, the first section is walked first.
+async fn set_store_sections(
+    conn: &mut PgConnection,
+    store_id: Uuid,
+    sections: &[String],
+) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM store_sections WHERE store_id = $1", store_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let mut position = 0;
+    let mut seen = Vec::with_capacity(sections.len());
+    for section in sections.iter().map(|section| normalize_section(section)) {
+        if section.is_empty() || seen.contains(&section) {
+            continue;
+        }
+        sqlx::query!(
+            "INSERT INTO store_sections (store_id, section, position) VALUES ($1, $2, $3)",
+            store_id,
+            section,
+            position
+        )
+        .execute(&mut *conn)
+        .await?;
+        position += 1;
+        seen.push(section);
+    }
+
+    Ok(())
+}
+
+#[derive(Deserialize)]
+pub struct StoreCreationInformation {
+    name: String,
+    #[serde(default)]
+    sections: Vec<String>,
+}
+#[derive(Serialize)]
+pub struct StoreCreationResponse {
+    id: Uuid,
+}
+
+pub async fn create_store(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Json(store): Json<StoreCreationInformation>,
+) -> Result<Json<StoreCreationResponse>, (StatusCode, String)> {
+    let error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to create store: {}", e),
+        )
+    };
+    let mut tx = state.db.begin().await.map_err(error)?;
+
+    let created = sqlx::query!(
+        "INSERT INTO stores (user_id, name) VALUES ($1, $2) RETURNING id",
+        user.id,
+        store.name
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(error)?;
+
+    set_store_sections(&mut tx, created.id, &store.sections)
+        .await
+        .map_err(error)?;
+
+    tx.commit().await.map_err(error)?;
+    Ok(Json(StoreCreationResponse { id: created.id }))
+}
+
+#[derive(Serialize)]
+pub struct Store {
+    pub id: Uuid,
+    pub name: String,
+    pub sections: Vec<String>,
+}
+#[derive(Serialize)]
+pub struct StoresResponse {
+    stores: Vec<Store>,
+}
+
+pub async fn get_stores(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+) -> Result<Json<StoresResponse>, (StatusCode, String)> {
+    let stores = sqlx::query_as!(
+        Store,
+        r#"
+        SELECT s.id, s.name,
+        COALESCE(
+            array_agg(a.section ORDER BY a.position) FILTER (WHERE a.section IS NOT NULL),
+            '{}'
+        ) as "sections!"
+        FROM stores s
+        LEFT JOIN store_sections a ON a.store_id = s.id
+        WHERE s.user_id = $1
+        GROUP BY s.id, s.name
+        ORDER BY s.name ASC
+        "#,
+        user.id
+    )
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to get stores: {}", e),
+        )
+    })?;
+
+    Ok(Json(StoresResponse { stores }))
+}
+
+#[derive(Deserialize)]
+pub struct StoreUpdateInformation {
+    name: Option<String>,
+    sections: Option<Vec<String>>,
+}
+
+pub async fn update_store(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(store): Json<StoreUpdateInformation>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let error = |e: sqlx::Error| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to update store: {}", e),
+        )
+    };
+    let mut tx = state.db.begin().await.map_err(error)?;
+
+    let result = sqlx::query!(
+        "UPDATE stores SET name = COALESCE($1, name) WHERE id = $2 AND user_id = $3",
+        store.name,
+        id,
+        user.id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(error)?;
+
+    if result.rows_affected() == 0 {
+        return Err((StatusCode::NOT_FOUND, "Store not found".to_string()));
+    }
+
+    if let Some(sections) = store.sections {
+        set_store_sections(&mut tx, id, &sections)
+            .await
+            .map_err(error)?;
+    }
+
+    tx.commit().await.map_err(error)?;
+    Ok(StatusCode::OK)
+}
+
+pub async fn delete_store(
+    Extension((user, _token)): Extension<(User, String)>,
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    sqlx::query!(
+        "DELETE FROM stores WHERE id = $1 AND user_id = $2",
+        id,
+        user.id
+    )
+    .execute(&state.db)
+    .await
+    .map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to delete store: {}", e),
+        )
+    })?;
+
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,47 @@
+use super::terms::normalize_terms;
+
+/// The 14 allergens EU law requires to be declared, anything else is kept as a custom allergen.
+pub const EU_ALLERGENS: &[&str] = &[
+    "celery",
+    "crustaceans",
+    "eggs",
+    "fish",
+    "gluten",
+    "lupin",
+    "milk",
+    "molluscs",
+    "mustard",
+    "nuts",
+    "peanuts",
+    "sesame",
+    "soybeans",
+    "sulphites",
+];
+
+/// Other names for the EU allergens, so "Soy" and "dairy" tag the same thing as "soybeans" and "milk".
+const SYNONYMS: &[(&str, &str)] = &[
+    ("crustacean", "crustaceans"),
+    ("egg", "eggs"),
+    ("wheat", "gluten"),
+    ("lupine", "lupin"),
+    ("dairy", "milk"),
+    ("lactose", "milk"),
+    ("mollusc", "molluscs"),
+    ("mollusks", "molluscs"),
+    ("tree nuts", "nuts"),
+    ("tree nut", "nuts"),
+    ("nut", "nuts"),
+    ("peanut", "peanuts"),
+    ("sesame seeds", "sesame"),
+    ("soy", "soybeans"),
+    ("soya", "soybeans"),
+    ("soybean", "soybeans"),
+    ("sulfites", "sulphites"),
+    ("sulphur dioxide", "sulphites"),
+    ("sulfur dioxide", "sulphites"),
+];
+
+/// Normalizes a list of allergens, sorted and without duplicates.
+pub fn normalize_allergens(allergens: &[String]) -> Vec<String> {
+    normalize_terms(allergens, SYNONYMS)
+}
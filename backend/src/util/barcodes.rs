@@ -0,0 +1,36 @@
+/// Checks the final digit of a GTIN, which covers EAN-8, UPC-A, EAN-13 and GTIN-14.
+fn has_valid_check_digit(digits: &[u32]) -> bool {
+    let Some((check, body)) = digits.split_last() else {
+        return false;
+    };
+    // Weights alternate 3, 1, … starting from the digit next to the check digit
+    let sum: u32 = body
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(index, digit)| if index % 2 == 0 { digit * 3 } else { *digit })
+        .sum();
+    (10 - sum % 10) % 10 == *check
+}
+
+/// Reduces a scanned code to one form, so the UPC-A "036000291452" and the EAN-13
+/// "0036000291452" are the same product. Returns `None` for anything that is not a valid code.
+pub fn normalize_barcode(code: &str) -> Option<String> {
+    let code: String = code
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect();
+    let digits: Vec<u32> = code
+        .chars()
+        .map(|c| c.to_digit(10))
+        .collect::<Option<_>>()?;
+    if !matches!(digits.len(), 8 | 12 | 13 | 14) || !has_valid_check_digit(&digits) {
+        return None;
+    }
+
+    match digits.len() {
+        12 => Some(format!("0{}", code)),
+        14 if code.starts_with('0') => Some(code[1..].to_string()),
+        _ => Some(code),
+    }
+}
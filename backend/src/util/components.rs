@@ -0,0 +1,33 @@
+use uuid::Uuid;
+
+/// Finds a path of components leading from a meal back to itself, such as pizza → dough → pizza.
+/// `edges` are the (meal, component) pairs reachable from the meal's new `components`.
+pub fn find_component_cycle(
+    meal_item_id: Uuid,
+    components: &[Uuid],
+    edges: &[(Uuid, Uuid)],
+) -> Option<Vec<Uuid>> {
+    let mut stack: Vec<Vec<Uuid>> = components
+        .iter()
+        .map(|component| vec![meal_item_id, *component])
+        .collect();
+    let mut visited = Vec::new();
+
+    while let Some(path) = stack.pop() {
+        let last = path[path.len() - 1];
+        if last == meal_item_id {
+            return Some(path);
+        }
+        if visited.contains(&last) {
+            continue;
+        }
+        visited.push(last);
+        for (_, component) in edges.iter().filter(|(meal, _)| *meal == last) {
+            let mut next = path.clone();
+            next.push(*component);
+            stack.push(next);
+        }
+    }
+
+    None
+}
@@ -0,0 +1,104 @@
+use serde::Serialize;
+use uuid::Uuid;
+
+use super::nutrition::convert_amount;
+
+/// What a user paid for `quantity` `unit` of an ingredient, such as 2.49 for 1 kg.
+#[derive(Debug, Clone)]
+pub struct Price {
+    pub price: f64,
+    pub quantity: f64,
+    pub unit: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CostIngredient {
+    pub id: Uuid,
+    pub name: String,
+    pub quantity: Option<f64>,
+    pub unit: Option<String>,
+    /// Grams per millilitre, lets a volume be priced by weight
+    pub density: Option<f64>,
+    /// Grams per piece, lets a count be priced by weight
+    pub piece_weight: Option<f64>,
+    pub price: Option<Price>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingCostReason {
+    NoAmount,
+    NoPrice,
+    /// The amount and the price are in units that cannot be converted
+    IncompatibleUnit,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MissingCost {
+    pub id: Uuid,
+    pub name: String,
+    pub reason: MissingCostReason,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MealCost {
+    pub servings: i32,
+    pub total: f64,
+    pub per_serving: f64,
+    /// True when every ingredient was priced, otherwise see `missing`
+    pub complete: bool,
+    pub missing: Vec<MissingCost>,
+}
+
+fn round_cents(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// Expresses an amount in the unit of a price.
+fn amount_in_price_units(ingredient: &CostIngredient, quantity: f64, price: &Price) -> Option<f64> {
+    convert_amount(
+        quantity,
+        ingredient.unit.as_deref(),
+        price.unit.as_deref(),
+        ingredient.density,
+        ingredient.piece_weight,
+    )
+}
+
+/// Prices a meal from the latest known price of each ingredient, flagging what could not be priced.
+pub fn meal_cost(ingredients: &[CostIngredient], servings: i32) -> MealCost {
+    let mut total = 0.0;
+    let mut missing = Vec::new();
+
+    for ingredient in ingredients {
+        let mut flag = |reason| {
+            missing.push(MissingCost {
+                id: ingredient.id,
+                name: ingredient.name.clone(),
+                reason,
+            })
+        };
+
+        let Some(price) = &ingredient.price else {
+            flag(MissingCostReason::NoPrice);
+            continue;
+        };
+        let Some(quantity) = ingredient.quantity else {
+            flag(MissingCostReason::NoAmount);
+            continue;
+        };
+        match amount_in_price_units(ingredient, quantity, price) {
+            Some(amount) => total += price.price * amount / price.quantity,
+            None => flag(MissingCostReason::IncompatibleUnit),
+        }
+    }
+
+    let servings = servings.max(1);
+    MealCost {
+        servings,
+        total: round_cents(total),
+        per_serving: round_cents(total / servings as f64),
+        complete: missing.is_empty(),
+        missing,
+    }
+}
@@ -0,0 +1,41 @@
+use serde::Deserialize;
+use uuid::Uuid;
+
+/// What to do with the rows that still point at something being deleted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeleteMode {
+    /// Refuse the delete while anything depends on it
+    #[default]
+    Restrict,
+    /// Delete the dependents as well
+    Cascade,
+    /// Keep the dependents but drop their reference
+    Nullify,
+}
+
+/// Postgres error code for a foreign key violation, raised by the array reference triggers too.
+pub const FOREIGN_KEY_VIOLATION: &str = "23503";
+
+pub fn is_foreign_key_violation(error: &sqlx::Error) -> bool {
+    error
+        .as_database_error()
+        .and_then(|error| error.code())
+        .is_some_and(|code| code == FOREIGN_KEY_VIOLATION)
+}
+
+/// Describes the blocking dependents, such as `used by 2 meals: Pasta (…), Salad (…)`.
+pub fn dependents_message(what: &str, kind: &str, dependents: &[(Uuid, String)]) -> String {
+    let listed: Vec<String> = dependents
+        .iter()
+        .map(|(id, name)| format!("{} ({})", name, id))
+        .collect();
+    format!(
+        "{} is used by {} {}{}: {}. Delete with mode=cascade or mode=nullify to continue",
+        what,
+        dependents.len(),
+        kind,
+        if dependents.len() == 1 { "" } else { "s" },
+        listed.join(", ")
+    )
+}
@@ -0,0 +1,307 @@
+use serde::{Deserialize, Serialize};
+
+use super::nutrition::MealNutrition;
+
+/// What an ingredient is made of, as far as diets are concerned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IngredientProperty {
+    Meat,
+    Fish,
+    Shellfish,
+    Dairy,
+    Eggs,
+    Honey,
+    Gelatin,
+    Gluten,
+}
+
+impl IngredientProperty {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            IngredientProperty::Meat => "meat",
+            IngredientProperty::Fish => "fish",
+            IngredientProperty::Shellfish => "shellfish",
+            IngredientProperty::Dairy => "dairy",
+            IngredientProperty::Eggs => "eggs",
+            IngredientProperty::Honey => "honey",
+            IngredientProperty::Gelatin => "gelatin",
+            IngredientProperty::Gluten => "gluten",
+        }
+    }
+
+    pub fn parse(property: &str) -> Option<Self> {
+        ALL_PROPERTIES
+            .iter()
+            .copied()
+            .find(|candidate| candidate.as_str() == property)
+    }
+}
+
+const ALL_PROPERTIES: &[IngredientProperty] = &[
+    IngredientProperty::Meat,
+    IngredientProperty::Fish,
+    IngredientProperty::Shellfish,
+    IngredientProperty::Dairy,
+    IngredientProperty::Eggs,
+    IngredientProperty::Honey,
+    IngredientProperty::Gelatin,
+    IngredientProperty::Gluten,
+];
+
+/// Allergen tags already say what an ingredient contains, so they count as properties too.
+const ALLERGEN_PROPERTIES: &[(&str, IngredientProperty)] = &[
+    ("crustaceans", IngredientProperty::Shellfish),
+    ("eggs", IngredientProperty::Eggs),
+    ("fish", IngredientProperty::Fish),
+    ("gluten", IngredientProperty::Gluten),
+    ("milk", IngredientProperty::Dairy),
+    ("molluscs", IngredientProperty::Shellfish),
+];
+
+/// Combines an ingredient's stored properties with the ones its allergens imply.
+pub fn ingredient_properties(
+    properties: &[String],
+    allergens: &[String],
+) -> Vec<IngredientProperty> {
+    let mut combined: Vec<IngredientProperty> = properties
+        .iter()
+        .filter_map(|property| IngredientProperty::parse(property))
+        .chain(allergens.iter().filter_map(|allergen| {
+            ALLERGEN_PROPERTIES
+                .iter()
+                .find(|(name, _)| name == allergen)
+                .map(|(_, property)| *property)
+        }))
+        .collect();
+    combined.sort_by_key(|property| property.as_str());
+    combined.dedup();
+    combined
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Diet {
+    Vegan,
+    Vegetarian,
+    GlutenFree,
+    Keto,
+}
+
+impl Diet {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Diet::Vegan => "vegan",
+            Diet::Vegetarian => "vegetarian",
+            Diet::GlutenFree => "gluten_free",
+            Diet::Keto => "keto",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Diet::Vegan => "vegan",
+            Diet::Vegetarian => "vegetarian",
+            Diet::GlutenFree => "gluten-free",
+            Diet::Keto => "keto",
+        }
+    }
+
+    pub fn parse(diet: &str) -> Option<Self> {
+        DIET_RULES
+            .iter()
+            .map(|rule| rule.diet)
+            .find(|candidate| candidate.as_str() == diet)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DietRule {
+    pub diet: Diet,
+    /// A meal with any ingredient that has one of these properties does not fit the diet
+    pub forbidden: &'static [IngredientProperty],
+    /// Carbohydrates minus fiber per serving, needs complete nutrition to be checked
+    pub max_net_carbs_per_serving: Option<f64>,
+}
+
+/// Every diet and what it rules out, classification and filtering both go by this table.
+pub const DIET_RULES: &[DietRule] = &[
+    DietRule {
+        diet: Diet::Vegan,
+        forbidden: &[
+            IngredientProperty::Meat,
+            IngredientProperty::Fish,
+            IngredientProperty::Shellfish,
+            IngredientProperty::Dairy,
+            IngredientProperty::Eggs,
+            IngredientProperty::Honey,
+            IngredientProperty::Gelatin,
+        ],
+        max_net_carbs_per_serving: None,
+    },
+    DietRule {
+        diet: Diet::Vegetarian,
+        forbidden: &[
+            IngredientProperty::Meat,
+            IngredientProperty::Fish,
+            IngredientProperty::Shellfish,
+            IngredientProperty::Gelatin,
+        ],
+        max_net_carbs_per_serving: None,
+    },
+    DietRule {
+        diet: Diet::GlutenFree,
+        forbidden: &[IngredientProperty::Gluten],
+        max_net_carbs_per_serving: None,
+    },
+    DietRule {
+        diet: Diet::Keto,
+        forbidden: &[],
+        max_net_carbs_per_serving: Some(10.0),
+    },
+];
+
+#[derive(Debug, Clone)]
+pub struct DietIngredient {
+    pub name: String,
+    pub properties: Vec<IngredientProperty>,
+    /// False until someone has recorded what the ingredient is made of
+    pub classified: bool,
+}
+
+/// A creator's ruling on a diet, which wins over the derived one.
+#[derive(Debug, Clone)]
+pub struct DietOverride {
+    pub diet: Diet,
+    pub applies: bool,
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DietLabel {
+    pub diet: Diet,
+    pub applies: bool,
+    pub overridden: bool,
+    /// Why the label applies or not, such as "not vegan: contains Honey"
+    pub reason: String,
+}
+
+fn derive_label(
+    rule: &DietRule,
+    ingredients: &[DietIngredient],
+    nutrition: &MealNutrition,
+) -> DietLabel {
+    let name = rule.diet.label();
+    let offending: Vec<&str> = ingredients
+        .iter()
+        .filter(|ingredient| {
+            ingredient
+                .properties
+                .iter()
+                .any(|property| rule.forbidden.contains(property))
+        })
+        .map(|ingredient| ingredient.name.as_str())
+        .collect();
+    if !offending.is_empty() {
+        return DietLabel {
+            diet: rule.diet,
+            applies: false,
+            overridden: false,
+            reason: format!("not {}: contains {}", name, offending.join(", ")),
+        };
+    }
+
+    // Like incomplete nutrition for keto, an unclassified ingredient could hide anything
+    let unclassified: Vec<&str> = ingredients
+        .iter()
+        .filter(|ingredient| !ingredient.classified)
+        .map(|ingredient| ingredient.name.as_str())
+        .collect();
+    if !rule.forbidden.is_empty() && !unclassified.is_empty() {
+        return DietLabel {
+            diet: rule.diet,
+            applies: false,
+            overridden: false,
+            reason: format!(
+                "not {}: {} {} not classified",
+                name,
+                unclassified.join(", "),
+                if unclassified.len() == 1 { "is" } else { "are" }
+            ),
+        };
+    }
+
+    let Some(max_net_carbs) = rule.max_net_carbs_per_serving else {
+        let ruled_out: Vec<&str> = rule
+            .forbidden
+            .iter()
+            .map(|property| property.as_str())
+            .collect();
+        return DietLabel {
+            diet: rule.diet,
+            applies: true,
+            overridden: false,
+            reason: format!("{}: no {}", name, ruled_out.join(", ")),
+        };
+    };
+
+    // Missing nutrition would undercount the carbs, so the label is withheld
+    if !nutrition.complete {
+        return DietLabel {
+            diet: rule.diet,
+            applies: false,
+            overridden: false,
+            reason: format!("not {}: nutrition is incomplete", name),
+        };
+    }
+    let net_carbs = nutrition.per_serving.carbohydrates - nutrition.per_serving.fiber;
+    DietLabel {
+        diet: rule.diet,
+        applies: net_carbs <= max_net_carbs,
+        overridden: false,
+        reason: format!(
+            "{}{}: {:.1} g net carbs per serving, at most {} g",
+            if net_carbs <= max_net_carbs {
+                ""
+            } else {
+                "not "
+            },
+            name,
+            net_carbs,
+            max_net_carbs
+        ),
+    }
+}
+
+/// Labels a meal with every diet, derived from its ingredients unless the creator overrode it.
+pub fn classify_meal(
+    ingredients: &[DietIngredient],
+    nutrition: &MealNutrition,
+    overrides: &[DietOverride],
+) -> Vec<DietLabel> {
+    DIET_RULES
+        .iter()
+        .map(|rule| {
+            let derived = derive_label(rule, ingredients, nutrition);
+            match overrides.iter().find(|o| o.diet == rule.diet) {
+                Some(rule_override) => DietLabel {
+                    diet: rule.diet,
+                    applies: rule_override.applies,
+                    overridden: true,
+                    reason: format!(
+                        "{}{}: set by the creator{}, otherwise {}",
+                        if rule_override.applies { "" } else { "not " },
+                        rule.diet.label(),
+                        rule_override
+                            .note
+                            .as_deref()
+                            .map(|note| format!(" ({})", note))
+                            .unwrap_or_default(),
+                        derived.reason
+                    ),
+                },
+                None => derived,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+use super::terms::normalize_terms;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    pub fn parse(difficulty: &str) -> Option<Self> {
+        [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard]
+            .into_iter()
+            .find(|candidate| candidate.as_str() == difficulty)
+    }
+}
+
+/// Equipment offered when tagging meals, anything else is kept as custom equipment.
+pub const COMMON_EQUIPMENT: &[&str] = &[
+    "air fryer",
+    "blender",
+    "dutch oven",
+    "food processor",
+    "grill",
+    "hand mixer",
+    "microwave",
+    "oven",
+    "pressure cooker",
+    "rice cooker",
+    "slow cooker",
+    "stand mixer",
+    "stovetop",
+    "thermometer",
+    "wok",
+];
+
+/// Other names for common equipment, so "Crock-Pot" and "slow cooker" are the same thing.
+const SYNONYMS: &[(&str, &str)] = &[
+    ("barbecue", "grill"),
+    ("bbq", "grill"),
+    ("crock-pot", "slow cooker"),
+    ("crockpot", "slow cooker"),
+    ("crock pot", "slow cooker"),
+    ("instant pot", "pressure cooker"),
+    ("multicooker", "pressure cooker"),
+    ("hob", "stovetop"),
+    ("stove", "stovetop"),
+    ("cooktop", "stovetop"),
+    ("range", "stovetop"),
+    ("kitchenaid", "stand mixer"),
+    ("mixer", "stand mixer"),
+    ("microwave oven", "microwave"),
+    ("oven thermometer", "thermometer"),
+    ("meat thermometer", "thermometer"),
+];
+
+/// Normalizes a list of equipment, sorted and without duplicates.
+pub fn normalize_equipment(equipment: &[String]) -> Vec<String> {
+    normalize_terms(equipment, SYNONYMS)
+}
@@ -0,0 +1,27 @@
+/// Endings too short to be plurals, "hummus" and "swiss" keep their final "s".
+const SINGULAR_ENDINGS: &[&str] = &["ss", "us", "is"];
+
+fn singular(word: &str) -> String {
+    if word.len() <= 3 || SINGULAR_ENDINGS.iter().any(|ending| word.ends_with(ending)) {
+        return word.to_string();
+    }
+    if let Some(stem) = word.strip_suffix("ies") {
+        return format!("{}y", stem);
+    }
+    for ending in ["oes", "ches", "shes", "xes", "zes"] {
+        if word.ends_with(ending) {
+            return word[..word.len() - 2].to_string();
+        }
+    }
+    word.strip_suffix('s').unwrap_or(word).to_string()
+}
+
+/// Reduces a name to the form duplicates share, so "Tomatoes" and "tomato" get the same key.
+pub fn ingredient_key(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(singular)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
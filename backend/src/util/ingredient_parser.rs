@@ -0,0 +1,482 @@
+use serde::Serialize;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ParsedIngredient {
+    pub quantity: Option<f64>,
+    /// Upper bound when the amount is a range such as "2-3"
+    pub quantity_max: Option<f64>,
+    pub unit: Option<String>,
+    pub ingredient: String,
+    pub note: Option<String>,
+}
+
+/// Canonical unit followed by the spellings that map to it, matched case-insensitively.
+const UNITS: &[(&str, &[&str])] = &[
+    ("tsp", &["tsp", "tsps", "ts", "teaspoon", "teaspoons"]),
+    (
+        "tbsp",
+        &[
+            "tbsp",
+            "tbsps",
+            "tbs",
+            "tbl",
+            "tb",
+            "tablespoon",
+            "tablespoons",
+        ],
+    ),
+    ("cup", &["c", "cup", "cups"]),
+    ("fl oz", &["floz", "fluid ounce", "fluid ounces"]),
+    ("pt", &["pt", "pint", "pints"]),
+    ("qt", &["qt", "quart", "quarts"]),
+    ("gal", &["gal", "gallon", "gallons"]),
+    (
+        "ml",
+        &[
+            "ml",
+            "milliliter",
+            "milliliters",
+            "millilitre",
+            "millilitres",
+        ],
+    ),
+    (
+        "cl",
+        &[
+            "cl",
+            "centiliter",
+            "centiliters",
+            "centilitre",
+            "centilitres",
+        ],
+    ),
+    (
+        "dl",
+        &["dl", "deciliter", "deciliters", "decilitre", "decilitres"],
+    ),
+    ("l", &["l", "liter", "liters", "litre", "litres"]),
+    ("mg", &["mg", "milligram", "milligrams"]),
+    ("g", &["g", "gr", "gram", "grams", "gramme", "grammes"]),
+    ("kg", &["kg", "kilo", "kilos", "kilogram", "kilograms"]),
+    ("oz", &["oz", "ounce", "ounces"]),
+    ("lb", &["lb", "lbs", "pound", "pounds"]),
+    ("pinch", &["pinch", "pinches"]),
+    ("dash", &["dash", "dashes"]),
+    ("clove", &["clove", "cloves"]),
+    ("can", &["can", "cans", "tin", "tins"]),
+    (
+        "package",
+        &["package", "packages", "pkg", "pkgs", "packet", "packets"],
+    ),
+    ("slice", &["slice", "slices"]),
+    ("stick", &["stick", "sticks"]),
+    ("bunch", &["bunch", "bunches"]),
+    ("handful", &["handful", "handfuls"]),
+    ("sprig", &["sprig", "sprigs"]),
+    ("piece", &["piece", "pieces", "pc", "pcs"]),
+];
+
+/// Words describing how an ingredient is prepared, moved from the name into the note.
+/// "ground" is left out, ground beef and ground cumin are ingredients of their own.
+const PREPARATIONS: &[&str] = &[
+    "finely",
+    "roughly",
+    "coarsely",
+    "thinly",
+    "thickly",
+    "freshly",
+    "lightly",
+    "chopped",
+    "minced",
+    "diced",
+    "sliced",
+    "grated",
+    "shredded",
+    "crushed",
+    "peeled",
+    "cubed",
+    "halved",
+    "quartered",
+    "melted",
+    "softened",
+    "beaten",
+    "sifted",
+    "drained",
+    "rinsed",
+    "julienned",
+    "toasted",
+    "packed",
+    "trimmed",
+    "zested",
+    "juiced",
+];
+
+const VULGAR_FRACTIONS: &[(char, &str)] = &[
+    ('½', "1/2"),
+    ('⅓', "1/3"),
+    ('⅔', "2/3"),
+    ('¼', "1/4"),
+    ('¾', "3/4"),
+    ('⅕', "1/5"),
+    ('⅖', "2/5"),
+    ('⅗', "3/5"),
+    ('⅘', "4/5"),
+    ('⅙', "1/6"),
+    ('⅚', "5/6"),
+    ('⅛', "1/8"),
+    ('⅜', "3/8"),
+    ('⅝', "5/8"),
+    ('⅞', "7/8"),
+];
+
+/// Looks up the canonical unit for a spelling such as "Tbsp." or "grams".
+pub fn canonical_unit(word: &str) -> Option<&'static str> {
+    // A capital T is the traditional shorthand for tablespoon, a lowercase t for teaspoon
+    match word.trim_end_matches('.') {
+        "T" => return Some("tbsp"),
+        "t" => return Some("tsp"),
+        _ => {}
+    }
+    let word = word.trim_end_matches('.').to_lowercase();
+    UNITS
+        .iter()
+        .find(|(_, spellings)| spellings.contains(&word.as_str()))
+        .map(|(unit, _)| *unit)
+}
+
+fn normalize(line: &str) -> String {
+    let mut normalized = String::with_capacity(line.len());
+    for c in line.chars() {
+        if let Some((_, fraction)) = VULGAR_FRACTIONS.iter().find(|(vulgar, _)| *vulgar == c) {
+            // "1½" reads as "1 1/2"
+            normalized.push(' ');
+            normalized.push_str(fraction);
+        } else if c == '⁄' {
+            normalized.push('/');
+        } else if c == '–' || c == '—' {
+            normalized.push('-');
+        } else {
+            normalized.push(c);
+        }
+    }
+    normalized
+}
+
+fn parse_number(word: &str) -> Option<f64> {
+    if let Some((numerator, denominator)) = word.split_once('/') {
+        let numerator: f64 = numerator.parse().ok()?;
+        let denominator: f64 = denominator.parse().ok()?;
+        return Some(numerator / denominator).filter(|n| n.is_finite());
+    }
+    word.replace(',', ".")
+        .parse()
+        .ok()
+        .filter(|n: &f64| n.is_finite())
+}
+
+fn is_fraction(word: &str) -> bool {
+    word.contains('/') && parse_number(word).is_some()
+}
+
+/// Reads a quantity from the start of `words`, returning it and the number of words used.
+fn parse_amount(words: &[String]) -> Option<(f64, usize)> {
+    let first = parse_number(words.first()?)?;
+    match words.get(1) {
+        // "1 1/2" is a whole number followed by a fraction
+        Some(next) if !words[0].contains('/') && is_fraction(next) => {
+            Some((first + parse_number(next)?, 2))
+        }
+        // So is "1-1/2", a fraction below one cannot be the top of a range starting at one
+        Some(next) if next == "-" && !words[0].contains('/') => {
+            match words.get(2).filter(|fraction| is_fraction(fraction)) {
+                Some(fraction) if parse_number(fraction)? < 1.0 => {
+                    Some((first + parse_number(fraction)?, 3))
+                }
+                _ => Some((first, 1)),
+            }
+        }
+        _ => Some((first, 1)),
+    }
+}
+
+fn split_words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for word in text.split_whitespace() {
+        // "200g" and "2-3" are split so amounts and units can be read separately
+        let mut current = String::new();
+        for c in word.chars() {
+            let after_digit = current.ends_with(|p: char| p.is_ascii_digit());
+            if c == '-' && after_digit {
+                words.push(std::mem::take(&mut current));
+                words.push("-".to_string());
+                continue;
+            }
+            if c.is_alphabetic() && after_digit {
+                words.push(std::mem::take(&mut current));
+            }
+            current.push(c);
+        }
+        if !current.is_empty() {
+            words.push(current);
+        }
+    }
+    words
+}
+
+/// Writes an amount back as a line `parse_ingredient_line` reads the same, such as "1.5 cup flour".
+pub fn format_ingredient_line(
+    quantity: Option<f64>,
+    unit: Option<&str>,
+    ingredient: &str,
+) -> String {
+    let quantity = quantity.map(|quantity| ((quantity * 1000.0).round() / 1000.0).to_string());
+    [quantity.as_deref(), unit, Some(ingredient)]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn parse_ingredient_line(line: &str) -> ParsedIngredient {
+    let mut notes = Vec::new();
+
+    // Parenthesised text such as "(14 oz)" is a note wherever it appears
+    let mut text = String::new();
+    let mut depth = 0;
+    let mut parenthetical = String::new();
+    for c in normalize(line).chars() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 && !parenthetical.trim().is_empty() {
+                    notes.push(parenthetical.trim().to_string());
+                    parenthetical.clear();
+                }
+            }
+            c if depth > 0 => parenthetical.push(c),
+            c => text.push(c),
+        }
+    }
+
+    // A comma between digits is a decimal separator rather than the start of a note
+    let bytes = text.as_bytes();
+    let comma = text.char_indices().find(|(i, c)| {
+        *c == ','
+            && !(*i > 0
+                && bytes[i - 1].is_ascii_digit()
+                && bytes.get(i + 1).is_some_and(u8::is_ascii_digit))
+    });
+    let (main, after_comma) = match comma {
+        Some((i, _)) => (
+            text[..i].to_string(),
+            Some(text[i + 1..].trim().to_string()),
+        ),
+        None => (text, None),
+    };
+
+    let words = split_words(&main);
+    let mut index = 0;
+    let mut parsed = ParsedIngredient::default();
+
+    if let Some((quantity, used)) = parse_amount(&words) {
+        parsed.quantity = Some(quantity);
+        index += used;
+        let is_range = matches!(
+            words.get(index).map(|word| word.to_lowercase()).as_deref(),
+            Some("-") | Some("to") | Some("or")
+        );
+        if is_range {
+            // "3-2" is not a range, the rest is left for the name where it shows up as odd
+            if let Some((quantity_max, used)) = parse_amount(&words[index + 1..])
+                .filter(|(quantity_max, _)| *quantity_max >= quantity)
+            {
+                parsed.quantity_max = Some(quantity_max);
+                index += 1 + used;
+            }
+        }
+    }
+
+    if let Some(word) = words.get(index) {
+        let two_words = words.get(index + 1).map(|next| {
+            format!(
+                "{}{}",
+                word.trim_end_matches('.'),
+                next.trim_end_matches('.')
+            )
+        });
+        let spaced = words
+            .get(index + 1)
+            .map(|next| format!("{} {}", word, next.trim_end_matches('.')));
+        if let Some(unit) = two_words
+            .as_deref()
+            .and_then(canonical_unit)
+            .or_else(|| spaced.as_deref().and_then(canonical_unit))
+        {
+            parsed.unit = Some(unit.to_string());
+            index += 2;
+        } else if let Some(unit) = canonical_unit(word) {
+            // A lone "l" or "c" only counts as a unit after an amount
+            if parsed.quantity.is_some() || word.len() > 2 {
+                parsed.unit = Some(unit.to_string());
+                index += 1;
+            }
+        }
+    }
+
+    if parsed.unit.is_some() && words.get(index).map(String::as_str) == Some("of") {
+        index += 1;
+    }
+
+    let mut preparation = Vec::new();
+    let mut name = Vec::new();
+    for word in &words[index..] {
+        if PREPARATIONS.contains(&word.to_lowercase().as_str()) {
+            preparation.push(word.as_str());
+        } else {
+            name.push(word.as_str());
+        }
+    }
+    let mut ingredient = name.join(" ");
+    for suffix in [" to taste", " for garnish", " for serving"] {
+        let split = ingredient.len().saturating_sub(suffix.len());
+        if ingredient.is_char_boundary(split) && ingredient[split..].eq_ignore_ascii_case(suffix) {
+            notes.push(ingredient[split + 1..].to_string());
+            ingredient.truncate(split);
+        }
+    }
+
+    if !preparation.is_empty() {
+        notes.insert(0, preparation.join(" "));
+    }
+    if let Some(after_comma) = after_comma.filter(|note| !note.is_empty()) {
+        notes.push(after_comma);
+    }
+
+    parsed.ingredient = ingredient.trim().to_string();
+    parsed.note = (!notes.is_empty()).then(|| notes.join(", "));
+    parsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(
+        quantity: Option<f64>,
+        quantity_max: Option<f64>,
+        unit: Option<&str>,
+        ingredient: &str,
+        note: Option<&str>,
+    ) -> ParsedIngredient {
+        ParsedIngredient {
+            quantity,
+            quantity_max,
+            unit: unit.map(str::to_string),
+            ingredient: ingredient.to_string(),
+            note: note.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn mixed_fraction_with_preparation() {
+        assert_eq!(
+            parse_ingredient_line("1 1/2 cups finely chopped onion"),
+            parsed(
+                Some(1.5),
+                None,
+                Some("cup"),
+                "onion",
+                Some("finely chopped")
+            )
+        );
+    }
+
+    #[test]
+    fn unicode_fraction() {
+        assert_eq!(
+            parse_ingredient_line("1½ cups"),
+            parsed(Some(1.5), None, Some("cup"), "", None)
+        );
+    }
+
+    #[test]
+    fn range() {
+        assert_eq!(
+            parse_ingredient_line("2-3 cloves garlic"),
+            parsed(Some(2.0), Some(3.0), Some("clove"), "garlic", None)
+        );
+    }
+
+    #[test]
+    fn hyphenated_mixed_fraction() {
+        assert_eq!(
+            parse_ingredient_line("1-1/2 cups flour"),
+            parsed(Some(1.5), None, Some("cup"), "flour", None)
+        );
+    }
+
+    #[test]
+    fn descending_range_is_not_a_range() {
+        assert_eq!(parse_ingredient_line("3-2 cups flour").quantity_max, None);
+    }
+
+    #[test]
+    fn ground_stays_in_the_name() {
+        assert_eq!(
+            parse_ingredient_line("1 lb ground beef"),
+            parsed(Some(1.0), None, Some("lb"), "ground beef", None)
+        );
+    }
+
+    #[test]
+    fn non_finite_fraction_is_not_an_amount() {
+        assert_eq!(parse_ingredient_line("inf/2 eggs").quantity, None);
+        assert_eq!(parse_ingredient_line("nan/1 eggs").quantity, None);
+    }
+
+    #[test]
+    fn unit_attached_to_amount() {
+        assert_eq!(
+            parse_ingredient_line("200g flour"),
+            parsed(Some(200.0), None, Some("g"), "flour", None)
+        );
+    }
+
+    #[test]
+    fn capital_t_is_tablespoon() {
+        assert_eq!(
+            parse_ingredient_line("1 T sugar"),
+            parsed(Some(1.0), None, Some("tbsp"), "sugar", None)
+        );
+        assert_eq!(
+            parse_ingredient_line("1 t sugar"),
+            parsed(Some(1.0), None, Some("tsp"), "sugar", None)
+        );
+    }
+
+    #[test]
+    fn comma_decimal() {
+        assert_eq!(
+            parse_ingredient_line("1,5 l milk"),
+            parsed(Some(1.5), None, Some("l"), "milk", None)
+        );
+    }
+
+    #[test]
+    fn to_taste_suffix() {
+        assert_eq!(
+            parse_ingredient_line("salt to taste"),
+            parsed(None, None, None, "salt", Some("to taste"))
+        );
+    }
+
+    #[test]
+    fn formatted_line_reads_back() {
+        let line = format_ingredient_line(Some(1.5), Some("fl oz"), "cream");
+        assert_eq!(
+            parse_ingredient_line(&line),
+            parsed(Some(1.5), None, Some("fl oz"), "cream", None)
+        );
+    }
+}
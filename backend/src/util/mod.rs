@@ -1,4 +1,26 @@
 pub mod account;
+pub mod allergens;
 pub mod app_state;
+pub mod barcodes;
+pub mod components;
+pub mod costs;
+pub mod deletion;
+pub mod diets;
+pub mod equipment;
+pub mod ingredient_names;
+pub mod ingredient_parser;
+pub mod nutrition;
+pub mod recipe_archive;
+pub mod recipe_export;
+pub mod reference_foods;
+pub mod schema_org;
+pub mod search;
+pub mod seasons;
 pub mod session;
+pub mod shopping_list;
+pub mod store_sections;
+pub mod substitutions;
+pub mod tags;
+pub mod terms;
+pub mod units;
 pub mod user;
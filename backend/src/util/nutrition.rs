@@ -0,0 +1,203 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::units::{convert, dimension, Dimension};
+
+/// Nutrition facts per 100 g, any of which may be unknown.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NutritionFacts {
+    /// Kilocalories
+    pub calories: Option<f64>,
+    pub protein: Option<f64>,
+    pub fat: Option<f64>,
+    pub carbohydrates: Option<f64>,
+    pub fiber: Option<f64>,
+    pub sugar: Option<f64>,
+    /// Milligrams, everything else is in grams
+    pub sodium: Option<f64>,
+}
+
+impl NutritionFacts {
+    fn values(&self) -> [Option<f64>; 7] {
+        [
+            self.calories,
+            self.protein,
+            self.fat,
+            self.carbohydrates,
+            self.fiber,
+            self.sugar,
+            self.sodium,
+        ]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values().iter().all(Option::is_none)
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.values().iter().all(Option::is_some)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NutritionTotals {
+    pub calories: f64,
+    pub protein: f64,
+    pub fat: f64,
+    pub carbohydrates: f64,
+    pub fiber: f64,
+    pub sugar: f64,
+    pub sodium: f64,
+}
+
+impl NutritionTotals {
+    fn add(&mut self, facts: &NutritionFacts, grams: f64) {
+        let scale = grams / 100.0;
+        self.calories += facts.calories.unwrap_or(0.0) * scale;
+        self.protein += facts.protein.unwrap_or(0.0) * scale;
+        self.fat += facts.fat.unwrap_or(0.0) * scale;
+        self.carbohydrates += facts.carbohydrates.unwrap_or(0.0) * scale;
+        self.fiber += facts.fiber.unwrap_or(0.0) * scale;
+        self.sugar += facts.sugar.unwrap_or(0.0) * scale;
+        self.sodium += facts.sodium.unwrap_or(0.0) * scale;
+    }
+
+    fn divided(&self, by: f64) -> NutritionTotals {
+        let round = |value: f64| (value / by * 10.0).round() / 10.0;
+        NutritionTotals {
+            calories: round(self.calories),
+            protein: round(self.protein),
+            fat: round(self.fat),
+            carbohydrates: round(self.carbohydrates),
+            fiber: round(self.fiber),
+            sugar: round(self.sugar),
+            sodium: round(self.sodium),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NutritionIngredient {
+    pub id: Uuid,
+    pub name: String,
+    pub facts: NutritionFacts,
+    pub quantity: Option<f64>,
+    pub unit: Option<String>,
+    /// Grams per millilitre, needed to weigh volumes
+    pub density: Option<f64>,
+    /// Grams per piece, needed to weigh counted units such as "2 eggs"
+    pub piece_weight: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MissingNutritionReason {
+    NoAmount,
+    NoDensity,
+    NoPieceWeight,
+    NoNutritionFacts,
+    IncompleteNutritionFacts,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MissingNutrition {
+    pub id: Uuid,
+    pub name: String,
+    pub reason: MissingNutritionReason,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MealNutrition {
+    pub servings: i32,
+    pub per_serving: NutritionTotals,
+    pub total: NutritionTotals,
+    /// True when every ingredient was weighed and had all facts, otherwise see `missing`
+    pub complete: bool,
+    pub missing: Vec<MissingNutrition>,
+}
+
+/// Weighs an amount in grams, using the ingredient's density or piece weight when needed.
+pub fn amount_in_grams(
+    quantity: f64,
+    unit: Option<&str>,
+    density: Option<f64>,
+    piece_weight: Option<f64>,
+) -> Result<f64, MissingNutritionReason> {
+    let (dimension, factor) = dimension(unit);
+    match dimension {
+        Dimension::Mass => Ok(quantity * factor),
+        Dimension::Volume => density
+            .map(|density| quantity * factor * density)
+            .ok_or(MissingNutritionReason::NoDensity),
+        Dimension::Count => piece_weight
+            .map(|piece_weight| quantity * piece_weight)
+            .ok_or(MissingNutritionReason::NoPieceWeight),
+    }
+}
+
+/// Converts an amount of one ingredient between units, going through grams when they measure
+/// different things, such as cups of flour into grams.
+pub fn convert_amount(
+    quantity: f64,
+    from: Option<&str>,
+    to: Option<&str>,
+    density: Option<f64>,
+    piece_weight: Option<f64>,
+) -> Option<f64> {
+    if let Some(converted) = convert(quantity, from, to) {
+        return Some(converted);
+    }
+    let weigh = |quantity, unit| amount_in_grams(quantity, unit, density, piece_weight).ok();
+    let grams = weigh(quantity, from)?;
+    let grams_per_unit = weigh(1.0, to).filter(|grams| *grams > 0.0)?;
+    Some(grams / grams_per_unit)
+}
+
+/// Adds up a meal's nutrition, flagging every ingredient that could not be fully counted.
+pub fn meal_nutrition(ingredients: &[NutritionIngredient], servings: i32) -> MealNutrition {
+    let mut total = NutritionTotals::default();
+    let mut missing = Vec::new();
+
+    for ingredient in ingredients {
+        let mut flag = |reason| {
+            missing.push(MissingNutrition {
+                id: ingredient.id,
+                name: ingredient.name.clone(),
+                reason,
+            })
+        };
+
+        if ingredient.facts.is_empty() {
+            flag(MissingNutritionReason::NoNutritionFacts);
+            continue;
+        }
+        let Some(quantity) = ingredient.quantity else {
+            flag(MissingNutritionReason::NoAmount);
+            continue;
+        };
+        match amount_in_grams(
+            quantity,
+            ingredient.unit.as_deref(),
+            ingredient.density,
+            ingredient.piece_weight,
+        ) {
+            Ok(grams) => {
+                total.add(&ingredient.facts, grams);
+                // Still counted, but the totals undercount whatever is unknown
+                if !ingredient.facts.is_complete() {
+                    flag(MissingNutritionReason::IncompleteNutritionFacts);
+                }
+            }
+            Err(reason) => flag(reason),
+        }
+    }
+
+    let servings = servings.max(1);
+    MealNutrition {
+        servings,
+        per_serving: total.divided(servings as f64),
+        total: total.divided(1.0),
+        complete: missing.is_empty(),
+        missing,
+    }
+}
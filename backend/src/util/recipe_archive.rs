@@ -0,0 +1,510 @@
+use std::io::{Cursor, Read};
+
+use flate2::read::GzDecoder;
+use serde::Deserialize;
+use serde_json::Value;
+use zip::ZipArchive;
+
+use super::schema_org::{parse_iso_duration, parse_recipe_documents, SchemaRecipe};
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveFormat {
+    Paprika,
+    Mealie,
+    Tandoor,
+    MealMaster,
+    JsonLd,
+}
+
+impl ArchiveFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Paprika => "paprika",
+            ArchiveFormat::Mealie => "mealie",
+            ArchiveFormat::Tandoor => "tandoor",
+            ArchiveFormat::MealMaster => "mealmaster",
+            ArchiveFormat::JsonLd => "jsonld",
+        }
+    }
+}
+
+/// One recipe found in an archive, failures are kept so they can be reported per item.
+#[derive(Debug)]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub recipe: Result<SchemaRecipe, String>,
+}
+
+impl ArchiveEntry {
+    fn failed(name: impl Into<String>, error: impl Into<String>) -> Self {
+        ArchiveEntry {
+            name: name.into(),
+            recipe: Err(error.into()),
+        }
+    }
+}
+
+/// Largest a single file may be once unpacked.
+const MAX_ENTRY_SIZE: u64 = 16 * 1024 * 1024;
+/// Largest everything in an archive may add up to once unpacked, so a small zip cannot fill memory.
+const MAX_UNPACKED_SIZE: u64 = 256 * 1024 * 1024;
+/// Tandoor puts a zip per recipe inside its export zip, anything deeper is not a recipe export.
+const MAX_NESTING_DEPTH: usize = 1;
+
+pub fn read_archive(format: ArchiveFormat, data: &[u8]) -> Result<Vec<ArchiveEntry>, String> {
+    let mut remaining = MAX_UNPACKED_SIZE;
+    match format {
+        ArchiveFormat::Paprika => read_paprika(data, &mut remaining),
+        ArchiveFormat::Mealie => read_json_export(data, mealie_recipe, 0, &mut remaining),
+        ArchiveFormat::Tandoor => read_json_export(data, tandoor_recipe, 0, &mut remaining),
+        ArchiveFormat::MealMaster => {
+            let text = String::from_utf8_lossy(data);
+            Ok(read_mealmaster(&text))
+        }
+        ArchiveFormat::JsonLd => read_json_ld(data, &mut remaining),
+    }
+}
+
+/// Covers PickyIt's own JSON-LD, HTML, Markdown and EPUB exports as well as saved recipe pages.
+fn read_json_ld(data: &[u8], remaining: &mut u64) -> Result<Vec<ArchiveEntry>, String> {
+    // An EPUB is a zip with the recipes embedded in its chapters
+    let documents = if data.starts_with(b"PK") {
+        zip_files(data, remaining)?
+            .into_iter()
+            .filter(|(file_name, _)| file_name.ends_with(".xhtml") || file_name.ends_with(".html"))
+            .map(|(_, contents)| String::from_utf8_lossy(&contents).into_owned())
+            .collect()
+    } else {
+        vec![String::from_utf8_lossy(data).into_owned()]
+    };
+
+    Ok(documents
+        .iter()
+        .flat_map(|document| parse_recipe_documents(document))
+        .enumerate()
+        .map(|(index, recipe)| ArchiveEntry {
+            name: recipe
+                .as_ref()
+                .map(|recipe| recipe.name.clone())
+                .unwrap_or_else(|_| format!("Recipe {}", index + 1)),
+            recipe,
+        })
+        .collect())
+}
+
+/// Reads at most `MAX_ENTRY_SIZE` bytes and whatever is left of the archive's unpacked size.
+fn read_limited(reader: impl Read, name: &str, remaining: &mut u64) -> Result<Vec<u8>, String> {
+    let limit = MAX_ENTRY_SIZE.min(*remaining);
+    let mut contents = Vec::new();
+    reader
+        .take(limit + 1)
+        .read_to_end(&mut contents)
+        .map_err(|e| format!("Failed to read {}: {}", name, e))?;
+    if contents.len() as u64 > limit {
+        return Err(if limit == MAX_ENTRY_SIZE {
+            format!(
+                "{} is larger than {} MB",
+                name,
+                MAX_ENTRY_SIZE / 1024 / 1024
+            )
+        } else {
+            format!(
+                "The archive is larger than {} MB once unpacked",
+                MAX_UNPACKED_SIZE / 1024 / 1024
+            )
+        });
+    }
+    *remaining -= contents.len() as u64;
+    Ok(contents)
+}
+
+fn zip_files(data: &[u8], remaining: &mut u64) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let mut archive =
+        ZipArchive::new(Cursor::new(data)).map_err(|e| format!("Invalid zip archive: {}", e))?;
+    let mut files = Vec::new();
+
+    for index in 0..archive.len() {
+        let mut file = archive
+            .by_index(index)
+            .map_err(|e| format!("Failed to read zip entry: {}", e))?;
+        if file.is_dir() {
+            continue;
+        }
+        let name = file.name().to_string();
+        let contents = read_limited(&mut file, &name, remaining)?;
+        files.push((name, contents));
+    }
+
+    Ok(files)
+}
+
+fn read_paprika(data: &[u8], remaining: &mut u64) -> Result<Vec<ArchiveEntry>, String> {
+    // A .paprikarecipes file is a zip where every entry is a gzipped JSON recipe
+    let entries = zip_files(data, remaining)?
+        .into_iter()
+        .map(|(file_name, contents)| {
+            let json =
+                match read_limited(GzDecoder::new(contents.as_slice()), &file_name, remaining) {
+                    Ok(json) => json,
+                    Err(e) => return ArchiveEntry::failed(file_name, e),
+                };
+            match serde_json::from_slice::<Value>(&json) {
+                Ok(value) => ArchiveEntry {
+                    name: text_field(&value, "name").unwrap_or(file_name),
+                    recipe: paprika_recipe(&value),
+                },
+                Err(e) => ArchiveEntry::failed(file_name, format!("Invalid JSON: {}", e)),
+            }
+        })
+        .collect();
+
+    Ok(entries)
+}
+
+fn paprika_recipe(value: &Value) -> Result<SchemaRecipe, String> {
+    Ok(SchemaRecipe {
+        name: text_field(value, "name").ok_or("Recipe has no name")?,
+        author: None,
+        ingredients: text_lines(text_field(value, "ingredients")),
+        instructions: text_lines(text_field(value, "directions")),
+        recipe_yield: text_field(value, "servings"),
+        prep_time: text_field(value, "prep_time").and_then(|time| parse_loose_duration(&time)),
+        cook_time: text_field(value, "cook_time").and_then(|time| parse_loose_duration(&time)),
+        total_time: text_field(value, "total_time").and_then(|time| parse_loose_duration(&time)),
+    })
+}
+
+fn read_json_export(
+    data: &[u8],
+    parse: fn(&Value) -> Result<SchemaRecipe, String>,
+    depth: usize,
+    remaining: &mut u64,
+) -> Result<Vec<ArchiveEntry>, String> {
+    // Exports are either a bare JSON document or a zip of them, Tandoor nests a zip per recipe
+    if !data.starts_with(b"PK") {
+        let value: Value =
+            serde_json::from_slice(data).map_err(|e| format!("Invalid JSON: {}", e))?;
+        let recipes = match value {
+            Value::Array(items) => items,
+            value => vec![value],
+        };
+        return Ok(recipes
+            .iter()
+            .enumerate()
+            .map(|(index, value)| ArchiveEntry {
+                name: text_field(value, "name").unwrap_or_else(|| format!("Recipe {}", index + 1)),
+                recipe: parse(value),
+            })
+            .collect());
+    }
+
+    let mut entries = Vec::new();
+    for (file_name, contents) in zip_files(data, remaining)? {
+        if file_name.ends_with(".zip") {
+            if depth >= MAX_NESTING_DEPTH {
+                entries.push(ArchiveEntry::failed(
+                    file_name,
+                    "Archives nested this deep are not recipe exports",
+                ));
+                continue;
+            }
+            match read_json_export(&contents, parse, depth + 1, remaining) {
+                Ok(nested) => entries.extend(nested),
+                Err(e) => entries.push(ArchiveEntry::failed(file_name, e)),
+            }
+        } else if file_name.ends_with(".json") {
+            match serde_json::from_slice::<Value>(&contents) {
+                Ok(value) => entries.push(ArchiveEntry {
+                    name: text_field(&value, "name").unwrap_or(file_name),
+                    recipe: parse(&value),
+                }),
+                Err(e) => entries.push(ArchiveEntry::failed(
+                    file_name,
+                    format!("Invalid JSON: {}", e),
+                )),
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+fn mealie_recipe(value: &Value) -> Result<SchemaRecipe, String> {
+    let ingredients = value
+        .get("recipeIngredient")
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| match item {
+                    Value::String(line) => Some(line.trim().to_string()),
+                    item => text_field(item, "originalText")
+                        .or_else(|| text_field(item, "display"))
+                        .or_else(|| text_field(item, "note")),
+                })
+                .filter(|line| !line.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let instructions = value
+        .get("recipeInstructions")
+        .and_then(Value::as_array)
+        .map(|steps| {
+            steps
+                .iter()
+                .filter_map(|step| match step {
+                    Value::String(text) => Some(text.trim().to_string()),
+                    step => text_field(step, "text"),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let duration = |key: &str| {
+        text_field(value, key)
+            .and_then(|time| parse_iso_duration(&time).or_else(|| parse_loose_duration(&time)))
+    };
+
+    Ok(SchemaRecipe {
+        name: text_field(value, "name").ok_or("Recipe has no name")?,
+        author: None,
+        ingredients,
+        instructions,
+        recipe_yield: text_field(value, "recipeYield"),
+        prep_time: duration("prepTime"),
+        cook_time: duration("performTime").or_else(|| duration("cookTime")),
+        total_time: duration("totalTime"),
+    })
+}
+
+fn tandoor_recipe(value: &Value) -> Result<SchemaRecipe, String> {
+    let mut ingredients = Vec::new();
+    let mut instructions = Vec::new();
+
+    for step in value
+        .get("steps")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        if let Some(instruction) = text_field(step, "instruction") {
+            instructions.extend(text_lines(Some(instruction)));
+        }
+        for ingredient in step
+            .get("ingredients")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            if let Some(line) = text_field(ingredient, "original_text") {
+                ingredients.push(line);
+                continue;
+            }
+            let parts: Vec<String> = [
+                ingredient
+                    .get("amount")
+                    .and_then(Value::as_f64)
+                    .filter(|amount| *amount > 0.0)
+                    .map(|amount| amount.to_string()),
+                ingredient
+                    .get("unit")
+                    .and_then(|unit| text_field(unit, "name")),
+                ingredient
+                    .get("food")
+                    .and_then(|food| text_field(food, "name")),
+                text_field(ingredient, "note").map(|note| format!(", {}", note)),
+            ]
+            .into_iter()
+            .flatten()
+            .collect();
+            if !parts.is_empty() {
+                ingredients.push(parts.join(" ").replace(" , ", ", "));
+            }
+        }
+    }
+
+    let minutes = |key: &str| {
+        value
+            .get(key)
+            .and_then(Value::as_i64)
+            .filter(|minutes| *minutes > 0)
+            .map(|minutes| minutes as i32)
+    };
+    let prep_time = minutes("working_time");
+    let cook_time = minutes("waiting_time");
+
+    Ok(SchemaRecipe {
+        name: text_field(value, "name").ok_or("Recipe has no name")?,
+        author: None,
+        ingredients,
+        instructions,
+        recipe_yield: value
+            .get("servings")
+            .and_then(Value::as_i64)
+            .map(|servings| servings.to_string()),
+        prep_time,
+        cook_time,
+        total_time: prep_time.zip(cook_time).map(|(prep, cook)| prep + cook),
+    })
+}
+
+fn read_mealmaster(text: &str) -> Vec<ArchiveEntry> {
+    let mut entries = Vec::new();
+    let mut current: Option<Vec<&str>> = None;
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        let is_header = (trimmed.starts_with("MMMMM") || trimmed.starts_with("-----"))
+            && trimmed.to_lowercase().contains("meal-master");
+        let is_footer = trimmed == "MMMMM" || trimmed == "-----";
+
+        if is_header {
+            if let Some(lines) = current.take() {
+                entries.push(mealmaster_entry(&lines));
+            }
+            current = Some(Vec::new());
+        } else if is_footer {
+            if let Some(lines) = current.take() {
+                entries.push(mealmaster_entry(&lines));
+            }
+        } else if let Some(lines) = current.as_mut() {
+            lines.push(line);
+        }
+    }
+    if let Some(lines) = current {
+        entries.push(mealmaster_entry(&lines));
+    }
+
+    entries
+}
+
+fn mealmaster_entry(lines: &[&str]) -> ArchiveEntry {
+    let mut name = None;
+    let mut recipe_yield = None;
+    let mut ingredients: Vec<String> = Vec::new();
+    let mut instructions = Vec::new();
+    let mut in_ingredients = false;
+
+    for line in lines {
+        let trimmed = line.trim();
+        if let Some(title) = trimmed.strip_prefix("Title:") {
+            name = Some(title.trim().to_string());
+            continue;
+        }
+        if trimmed.starts_with("Categories:") {
+            continue;
+        }
+        if let Some(servings) = trimmed
+            .strip_prefix("Yield:")
+            .or_else(|| trimmed.strip_prefix("Servings:"))
+        {
+            recipe_yield = Some(servings.trim().to_string());
+            in_ingredients = true;
+            continue;
+        }
+        if trimmed.is_empty() {
+            // The ingredient block ends at the first blank line after it started
+            if in_ingredients && !ingredients.is_empty() {
+                in_ingredients = false;
+            }
+            continue;
+        }
+        if in_ingredients {
+            // Section headings look like "MMMMM--------------DOUGH---------------"
+            if trimmed.starts_with("MMMMM") || trimmed.starts_with("-----") {
+                continue;
+            }
+            // Lines starting with "-" continue the previous ingredient
+            match (trimmed.strip_prefix('-'), ingredients.last_mut()) {
+                (Some(rest), Some(last)) if line.starts_with(' ') => {
+                    last.push(' ');
+                    last.push_str(rest.trim());
+                }
+                _ => ingredients.push(trimmed.split_whitespace().collect::<Vec<_>>().join(" ")),
+            }
+        } else {
+            instructions.push(trimmed.to_string());
+        }
+    }
+
+    match name {
+        Some(name) if !name.is_empty() => ArchiveEntry {
+            name: name.clone(),
+            recipe: Ok(SchemaRecipe {
+                name,
+                ingredients,
+                instructions,
+                recipe_yield,
+                ..Default::default()
+            }),
+        },
+        _ => ArchiveEntry::failed("Untitled", "Recipe has no title"),
+    }
+}
+
+fn text_field(value: &Value, key: &str) -> Option<String> {
+    match value.get(key)? {
+        Value::String(text) => Some(text.trim().to_string()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+    .filter(|text| !text.is_empty())
+}
+
+fn text_lines(text: Option<String>) -> Vec<String> {
+    text.map(|text| {
+        text.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+/// Reads human written durations such as "1 hr 15 mins" or "45 minutes".
+pub fn parse_loose_duration(text: &str) -> Option<i32> {
+    let text = text.to_lowercase();
+    let mut minutes = 0.0;
+    let mut found = false;
+    let mut amount: Option<f64> = None;
+
+    for word in text.split(|c: char| c.is_whitespace() || c == ',') {
+        if word.is_empty() {
+            continue;
+        }
+        let split = word
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(word.len());
+        let (number, unit) = word.split_at(split);
+        if !number.is_empty() {
+            amount = number.parse().ok();
+        }
+        if unit.is_empty() {
+            continue;
+        }
+        if let Some(value) = amount.take() {
+            let factor = if unit.starts_with('h') {
+                60.0
+            } else if unit.starts_with('m') {
+                1.0
+            } else if unit.starts_with('d') {
+                24.0 * 60.0
+            } else {
+                continue;
+            };
+            minutes += value * factor;
+            found = true;
+        }
+    }
+
+    // A bare number is taken as minutes
+    if let Some(value) = amount {
+        minutes += value;
+        found = true;
+    }
+
+    found.then_some(minutes.round() as i32)
+}
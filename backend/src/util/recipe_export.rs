@@ -0,0 +1,297 @@
+use std::io::{Cursor, Write};
+
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::Value;
+use uuid::Uuid;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use super::schema_org::{recipe_to_json_ld, SchemaRecipe, JSON_LD_FENCE};
+
+/// Every format carries the recipes as schema.org JSON-LD, so an export imports again as `jsonld`.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    JsonLd,
+    Markdown,
+    /// Styled for printing, printing it to a file is how a PDF is made
+    Html,
+    Epub,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::JsonLd => "application/ld+json",
+            ExportFormat::Markdown => "text/markdown; charset=utf-8",
+            ExportFormat::Html => "text/html; charset=utf-8",
+            ExportFormat::Epub => "application/epub+zip",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::JsonLd => "jsonld",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+            ExportFormat::Epub => "epub",
+        }
+    }
+}
+
+pub fn export_recipes(
+    format: ExportFormat,
+    title: &str,
+    recipes: &[SchemaRecipe],
+) -> Result<Vec<u8>, String> {
+    match format {
+        ExportFormat::JsonLd => {
+            let value = match recipes {
+                [recipe] => recipe_to_json_ld(recipe),
+                recipes => Value::Array(recipes.iter().map(recipe_to_json_ld).collect()),
+            };
+            serde_json::to_vec_pretty(&value).map_err(|e| format!("Failed to write JSON-LD: {}", e))
+        }
+        ExportFormat::Markdown => Ok(recipes
+            .iter()
+            .map(recipe_markdown)
+            .collect::<Vec<_>>()
+            .join("\n---\n\n")
+            .into_bytes()),
+        ExportFormat::Html => Ok(html_document(title, recipes).into_bytes()),
+        ExportFormat::Epub => epub_document(title, recipes),
+    }
+}
+
+fn recipe_details(recipe: &SchemaRecipe) -> Vec<String> {
+    let mut details = Vec::new();
+    if let Some(recipe_yield) = &recipe.recipe_yield {
+        details.push(format!("Serves: {}", recipe_yield));
+    }
+    for (label, minutes) in [
+        ("Prep", recipe.prep_time),
+        ("Cook", recipe.cook_time),
+        ("Total", recipe.total_time),
+    ] {
+        if let Some(minutes) = minutes {
+            details.push(format!("{}: {} min", label, minutes));
+        }
+    }
+    details
+}
+
+fn recipe_markdown(recipe: &SchemaRecipe) -> String {
+    let mut markdown = format!("# {}\n\n", recipe.name);
+    if let Some(author) = &recipe.author {
+        markdown.push_str(&format!("*By {}*\n\n", author));
+    }
+    let details = recipe_details(recipe);
+    if !details.is_empty() {
+        markdown.push_str(&format!("{}\n\n", details.join(" · ")));
+    }
+
+    markdown.push_str("## Ingredients\n\n");
+    for ingredient in &recipe.ingredients {
+        markdown.push_str(&format!("- {}\n", ingredient));
+    }
+
+    markdown.push_str("\n## Instructions\n\n");
+    for (index, step) in recipe.instructions.iter().enumerate() {
+        markdown.push_str(&format!("{}. {}\n", index + 1, step));
+    }
+
+    // The recipe again as JSON-LD so the file can be imported, JSON strings never hold a raw newline
+    // so the closing fence cannot show up inside the block
+    markdown.push_str(&format!(
+        "\n```{}\n{}\n```\n",
+        JSON_LD_FENCE,
+        serde_json::to_string_pretty(&recipe_to_json_ld(recipe)).unwrap()
+    ));
+
+    markdown
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn recipe_html(recipe: &SchemaRecipe) -> String {
+    let mut html = format!("<article>\n<h1>{}</h1>\n", escape_html(&recipe.name));
+    if let Some(author) = &recipe.author {
+        html.push_str(&format!(
+            "<p class=\"author\">By {}</p>\n",
+            escape_html(author)
+        ));
+    }
+    let details = recipe_details(recipe);
+    if !details.is_empty() {
+        html.push_str(&format!(
+            "<p class=\"details\">{}</p>\n",
+            escape_html(&details.join(" · "))
+        ));
+    }
+
+    html.push_str("<h2>Ingredients</h2>\n<ul>\n");
+    for ingredient in &recipe.ingredients {
+        html.push_str(&format!("<li>{}</li>\n", escape_html(ingredient)));
+    }
+    html.push_str("</ul>\n<h2>Instructions</h2>\n<ol>\n");
+    for step in &recipe.instructions {
+        html.push_str(&format!("<li>{}</li>\n", escape_html(step)));
+    }
+    html.push_str("</ol>\n</article>\n");
+
+    html
+}
+
+fn json_ld_script(recipe: &SchemaRecipe) -> String {
+    // Markup characters only occur inside JSON strings, where escapes keep them from ending the
+    // script element in HTML or breaking the XML of an EPUB chapter
+    let json = recipe_to_json_ld(recipe)
+        .to_string()
+        .replace('<', "\\u003c")
+        .replace('>', "\\u003e")
+        .replace('&', "\\u0026");
+    format!("<script type=\"application/ld+json\">{}</script>\n", json)
+}
+
+const PRINT_STYLE: &str =
+    "body { font-family: Georgia, serif; max-width: 40em; margin: 2em auto; line-height: 1.5; }
+article { page-break-after: always; }
+article:last-child { page-break-after: auto; }
+.author, .details { color: #555; }
+@media print { body { margin: 0; } }";
+
+fn html_document(title: &str, recipes: &[SchemaRecipe]) -> String {
+    // The embedded JSON-LD lets the page be imported again as it is
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}\n</style>\n",
+        escape_html(title),
+        PRINT_STYLE
+    );
+    for recipe in recipes {
+        html.push_str(&json_ld_script(recipe));
+    }
+    html.push_str("</head>\n<body>\n");
+    for recipe in recipes {
+        html.push_str(&recipe_html(recipe));
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn xhtml_document(title: &str, head: &str, body: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<!DOCTYPE html>\n<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\">\n<head>\n<title>{}</title>\n<style>\n{}\n</style>\n{}</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(title),
+        PRINT_STYLE,
+        head,
+        body
+    )
+}
+
+fn epub_document(title: &str, recipes: &[SchemaRecipe]) -> Result<Vec<u8>, String> {
+    let write_error = |e: std::io::Error| format!("Failed to write EPUB: {}", e);
+    let zip_error = |e: zip::result::ZipError| format!("Failed to write EPUB: {}", e);
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    // The mimetype entry has to come first and stay uncompressed
+    zip.start_file("mimetype", stored).map_err(zip_error)?;
+    zip.write_all(b"application/epub+zip")
+        .map_err(write_error)?;
+
+    zip.start_file("META-INF/container.xml", deflated)
+        .map_err(zip_error)?;
+    zip.write_all(
+        br#"<?xml version="1.0" encoding="utf-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+<rootfiles>
+<rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+</rootfiles>
+</container>
+"#,
+    )
+    .map_err(write_error)?;
+
+    let mut manifest = String::new();
+    let mut spine = String::new();
+    let mut toc = String::new();
+    for (index, recipe) in recipes.iter().enumerate() {
+        let file_name = format!("recipe-{}.xhtml", index + 1);
+        zip.start_file(format!("OEBPS/{}", file_name), deflated)
+            .map_err(zip_error)?;
+        // Each chapter carries its recipe as JSON-LD like the HTML export, so the book imports again
+        zip.write_all(
+            xhtml_document(&recipe.name, &json_ld_script(recipe), &recipe_html(recipe)).as_bytes(),
+        )
+        .map_err(write_error)?;
+
+        manifest.push_str(&format!(
+            "<item id=\"recipe-{}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>\n",
+            index + 1,
+            file_name
+        ));
+        spine.push_str(&format!("<itemref idref=\"recipe-{}\"/>\n", index + 1));
+        toc.push_str(&format!(
+            "<li><a href=\"{}\">{}</a></li>\n",
+            file_name,
+            escape_html(&recipe.name)
+        ));
+    }
+
+    zip.start_file("OEBPS/nav.xhtml", deflated)
+        .map_err(zip_error)?;
+    zip.write_all(
+        xhtml_document(
+            title,
+            "",
+            &format!(
+                "<nav epub:type=\"toc\">\n<h1>{}</h1>\n<ol>\n{}</ol>\n</nav>\n",
+                escape_html(title),
+                toc
+            ),
+        )
+        .as_bytes(),
+    )
+    .map_err(write_error)?;
+
+    zip.start_file("OEBPS/content.opf", deflated)
+        .map_err(zip_error)?;
+    zip.write_all(
+        format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>
+<package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"book-id\">
+<metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">
+<dc:identifier id=\"book-id\">urn:uuid:{}</dc:identifier>
+<dc:title>{}</dc:title>
+<dc:language>en</dc:language>
+<meta property=\"dcterms:modified\">{}</meta>
+</metadata>
+<manifest>
+<item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>
+{}</manifest>
+<spine>
+<itemref idref=\"nav\"/>
+{}</spine>
+</package>
+",
+            Uuid::new_v4(),
+            escape_html(title),
+            Utc::now().format("%Y-%m-%dT%H:%M:%SZ"),
+            manifest,
+            spine
+        )
+        .as_bytes(),
+    )
+    .map_err(write_error)?;
+
+    let cursor = zip.finish().map_err(zip_error)?;
+    Ok(cursor.into_inner())
+}
@@ -0,0 +1,278 @@
+use std::collections::HashMap;
+use std::io::Read;
+
+use serde::Serialize;
+
+use super::{barcodes::normalize_barcode, nutrition::NutritionFacts};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReferenceSource {
+    Usda,
+    OpenFoodFacts,
+}
+
+impl ReferenceSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReferenceSource::Usda => "usda",
+            ReferenceSource::OpenFoodFacts => "openfoodfacts",
+        }
+    }
+
+    pub fn parse(source: &str) -> Option<Self> {
+        match source.to_lowercase().as_str() {
+            "usda" | "fdc" => Some(ReferenceSource::Usda),
+            "openfoodfacts" | "off" => Some(ReferenceSource::OpenFoodFacts),
+            _ => None,
+        }
+    }
+}
+
+/// One food from a dataset with its nutrition per 100 g.
+#[derive(Debug, Clone)]
+pub struct ReferenceFood {
+    pub source_id: String,
+    pub name: String,
+    pub brand: Option<String>,
+    pub barcode: Option<String>,
+    pub facts: NutritionFacts,
+}
+
+/// Reference names share the 255 character limit of ingredient names.
+const MAX_NAME_LENGTH: usize = 255;
+
+fn clean_name(name: &str) -> Option<String> {
+    let name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    if name.is_empty() {
+        return None;
+    }
+    Some(name.chars().take(MAX_NAME_LENGTH).collect())
+}
+
+fn parse_amount(value: &str) -> Option<f64> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|amount| amount.is_finite() && *amount >= 0.0)
+}
+
+fn column(headers: &csv::StringRecord, name: &str) -> Result<usize, String> {
+    headers
+        .iter()
+        .position(|header| header == name)
+        .ok_or_else(|| format!("Missing column \"{}\"", name))
+}
+
+#[derive(Clone, Copy)]
+enum Nutrient {
+    Calories,
+    Protein,
+    Fat,
+    Carbohydrates,
+    Fiber,
+    Sugar,
+    Sodium,
+}
+
+/// FoodData Central nutrient ids, earlier entries win when a food reports several.
+const USDA_NUTRIENTS: &[(&str, Nutrient)] = &[
+    ("1008", Nutrient::Calories),
+    ("2047", Nutrient::Calories),
+    ("2048", Nutrient::Calories),
+    ("1003", Nutrient::Protein),
+    ("1004", Nutrient::Fat),
+    ("1005", Nutrient::Carbohydrates),
+    ("1050", Nutrient::Carbohydrates),
+    ("1079", Nutrient::Fiber),
+    ("2000", Nutrient::Sugar),
+    ("1063", Nutrient::Sugar),
+    ("1093", Nutrient::Sodium),
+];
+
+fn set_nutrient(facts: &mut NutritionFacts, nutrient: Nutrient, amount: f64) {
+    let field = match nutrient {
+        Nutrient::Calories => &mut facts.calories,
+        Nutrient::Protein => &mut facts.protein,
+        Nutrient::Fat => &mut facts.fat,
+        Nutrient::Carbohydrates => &mut facts.carbohydrates,
+        Nutrient::Fiber => &mut facts.fiber,
+        Nutrient::Sugar => &mut facts.sugar,
+        Nutrient::Sodium => &mut facts.sodium,
+    };
+    field.get_or_insert(amount);
+}
+
+/// Brand and barcode of a branded food.
+type Branding = (Option<String>, Option<String>);
+
+/// Reads the brand and barcode of branded foods from a FoodData Central `branded_food.csv`.
+fn read_usda_brands(branded_food: impl Read) -> Result<HashMap<String, Branding>, String> {
+    let mut branded = csv::Reader::from_reader(branded_food);
+    let headers = branded.headers().map_err(|e| e.to_string())?.clone();
+    let (fdc_id, brand_owner, gtin_upc) = (
+        column(&headers, "fdc_id")?,
+        column(&headers, "brand_owner")?,
+        column(&headers, "gtin_upc")?,
+    );
+
+    let mut brands = HashMap::new();
+    for record in branded.records() {
+        let record = record.map_err(|e| e.to_string())?;
+        brands.insert(
+            record[fdc_id].to_string(),
+            (
+                clean_name(&record[brand_owner]),
+                normalize_barcode(&record[gtin_upc]),
+            ),
+        );
+    }
+
+    Ok(brands)
+}
+
+/// Reads a FoodData Central CSV download from its `food.csv` and `food_nutrient.csv` files,
+/// the Branded Foods download also has a `branded_food.csv` with brands and barcodes.
+///
+/// `food_nutrient.csv` is not ordered by food, so every food's name, brand and nutrients are
+/// held in memory until all files are read. That is a few hundred bytes per food, around
+/// two gigabytes for the full Branded Foods download of nearly two million foods.
+pub fn read_usda(
+    food: impl Read,
+    food_nutrient: impl Read,
+    branded_food: Option<impl Read>,
+) -> Result<Vec<ReferenceFood>, String> {
+    let mut brands = match branded_food {
+        Some(branded_food) => read_usda_brands(branded_food)?,
+        None => HashMap::new(),
+    };
+
+    let mut foods = csv::Reader::from_reader(food);
+    let headers = foods.headers().map_err(|e| e.to_string())?.clone();
+    let (fdc_id, description) = (
+        column(&headers, "fdc_id")?,
+        column(&headers, "description")?,
+    );
+
+    let mut names = HashMap::new();
+    for record in foods.records() {
+        let record = record.map_err(|e| e.to_string())?;
+        if let Some(name) = clean_name(&record[description]) {
+            names.insert(record[fdc_id].to_string(), name);
+        }
+    }
+
+    let mut nutrients = csv::Reader::from_reader(food_nutrient);
+    let headers = nutrients.headers().map_err(|e| e.to_string())?.clone();
+    let (food_id, nutrient_id, amount) = (
+        column(&headers, "fdc_id")?,
+        column(&headers, "nutrient_id")?,
+        column(&headers, "amount")?,
+    );
+
+    // Collected per priority first so the preferred nutrient id wins regardless of row order
+    let mut found: HashMap<String, Vec<(usize, Nutrient, f64)>> = HashMap::new();
+    for record in nutrients.records() {
+        let record = record.map_err(|e| e.to_string())?;
+        let Some(priority) = USDA_NUTRIENTS
+            .iter()
+            .position(|(id, _)| *id == &record[nutrient_id])
+        else {
+            continue;
+        };
+        if let Some(value) = parse_amount(&record[amount]) {
+            found.entry(record[food_id].to_string()).or_default().push((
+                priority,
+                USDA_NUTRIENTS[priority].1,
+                value,
+            ));
+        }
+    }
+
+    let mut reference_foods: Vec<ReferenceFood> = found
+        .into_iter()
+        .filter_map(|(source_id, mut values)| {
+            let name = names.remove(&source_id)?;
+            let (brand, barcode) = brands.remove(&source_id).unwrap_or_default();
+            values.sort_by_key(|(priority, _, _)| *priority);
+            let mut facts = NutritionFacts::default();
+            for (_, nutrient, value) in values {
+                set_nutrient(&mut facts, nutrient, value);
+            }
+            Some(ReferenceFood {
+                source_id,
+                name,
+                brand,
+                barcode,
+                facts,
+            })
+        })
+        .collect();
+    reference_foods.sort_by(|a, b| a.source_id.cmp(&b.source_id));
+
+    Ok(reference_foods)
+}
+
+/// Reads the tab separated Open Food Facts product export, skipping products without nutrition.
+pub fn read_open_food_facts(
+    products: impl Read,
+) -> Result<impl Iterator<Item = Result<ReferenceFood, String>>, String> {
+    // The export is not quoted, quotes inside product names are literal
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b'\t')
+        .quoting(false)
+        .flexible(true)
+        .from_reader(products);
+    let headers = reader.headers().map_err(|e| e.to_string())?.clone();
+    let code = column(&headers, "code")?;
+    let product_name = column(&headers, "product_name")?;
+    let brands = headers.iter().position(|header| header == "brands");
+    let nutrients = [
+        ("energy-kcal_100g", Nutrient::Calories, 1.0),
+        ("proteins_100g", Nutrient::Protein, 1.0),
+        ("fat_100g", Nutrient::Fat, 1.0),
+        ("carbohydrates_100g", Nutrient::Carbohydrates, 1.0),
+        ("fiber_100g", Nutrient::Fiber, 1.0),
+        ("sugars_100g", Nutrient::Sugar, 1.0),
+        // Open Food Facts reports sodium in grams
+        ("sodium_100g", Nutrient::Sodium, 1000.0),
+    ]
+    .map(|(name, nutrient, factor)| (headers.iter().position(|h| h == name), nutrient, factor));
+
+    Ok(reader.into_records().filter_map(move |record| {
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => return Some(Err(e.to_string())),
+        };
+        let source_id = record.get(code)?.trim().to_string();
+        let name = clean_name(record.get(product_name)?)?;
+        if source_id.is_empty() {
+            return None;
+        }
+
+        let mut facts = NutritionFacts::default();
+        for (index, nutrient, factor) in nutrients {
+            if let Some(value) = index
+                .and_then(|index| record.get(index))
+                .and_then(parse_amount)
+            {
+                set_nutrient(&mut facts, nutrient, value * factor);
+            }
+        }
+        if facts.is_empty() {
+            return None;
+        }
+
+        Some(Ok(ReferenceFood {
+            barcode: normalize_barcode(&source_id),
+            source_id,
+            name,
+            // Several brands are comma separated, the first is the owner
+            brand: brands
+                .and_then(|index| record.get(index))
+                .and_then(|brands| clean_name(brands.split(',').next().unwrap_or_default())),
+            facts,
+        }))
+    }))
+}
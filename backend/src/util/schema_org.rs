@@ -0,0 +1,312 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SchemaRecipe {
+    pub name: String,
+    pub author: Option<String>,
+    pub ingredients: Vec<String>,
+    pub instructions: Vec<String>,
+    pub recipe_yield: Option<String>,
+    pub prep_time: Option<i32>,
+    pub cook_time: Option<i32>,
+    pub total_time: Option<i32>,
+}
+
+/// Finds the first schema.org Recipe in either an HTML page or a raw JSON-LD document.
+pub fn parse_recipe_document(document: &str) -> Result<SchemaRecipe, String> {
+    parse_recipe_documents(document)
+        .into_iter()
+        .next()
+        .unwrap_or(Err("No schema.org Recipe found in document".to_string()))
+}
+
+/// Info string of the fenced code blocks holding JSON-LD in PickyIt's Markdown export.
+pub const JSON_LD_FENCE: &str = "ld+json";
+
+/// Finds every schema.org Recipe in a document, such as a cookbook exported from PickyIt.
+pub fn parse_recipe_documents(document: &str) -> Vec<Result<SchemaRecipe, String>> {
+    let trimmed = document.trim_start();
+    let blocks = if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        vec![trimmed.to_string()]
+    } else {
+        let mut blocks = json_ld_blocks(document);
+        blocks.extend(fenced_json_ld_blocks(document));
+        blocks
+    };
+
+    let mut recipes = Vec::new();
+    for block in blocks {
+        let value: Value = match serde_json::from_str(&block) {
+            Ok(value) => value,
+            Err(_) => continue,
+        };
+        let mut found = Vec::new();
+        find_recipes(&value, &mut found);
+        recipes.extend(found.into_iter().map(recipe_from_value));
+    }
+
+    recipes
+}
+
+fn json_ld_blocks(html: &str) -> Vec<String> {
+    // Tags are matched on a lowercased copy, ASCII lowercasing keeps the byte offsets aligned
+    let lower = html.to_ascii_lowercase();
+    let mut blocks = Vec::new();
+    let mut cursor = 0;
+
+    while let Some(offset) = lower[cursor..].find("<script") {
+        let tag_start = cursor + offset;
+        let tag_end = match lower[tag_start..].find('>') {
+            Some(end) => tag_start + end + 1,
+            None => break,
+        };
+        let content_end = match lower[tag_end..].find("</script") {
+            Some(end) => tag_end + end,
+            None => break,
+        };
+        if lower[tag_start..tag_end].contains("application/ld+json") {
+            blocks.push(html[tag_end..content_end].trim().to_string());
+        }
+        cursor = content_end;
+    }
+
+    blocks
+}
+
+fn fenced_json_ld_blocks(markdown: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut block: Option<Vec<&str>> = None;
+
+    for line in markdown.lines() {
+        let fence = line.trim();
+        match &mut block {
+            None if fence.strip_prefix("```") == Some(JSON_LD_FENCE) => block = Some(Vec::new()),
+            None => {}
+            Some(lines) if fence == "```" => {
+                blocks.push(lines.join("\n"));
+                block = None;
+            }
+            Some(lines) => lines.push(line),
+        }
+    }
+
+    blocks
+}
+
+fn is_recipe(value: &Value) -> bool {
+    match value.get("@type") {
+        Some(Value::String(kind)) => kind == "Recipe",
+        Some(Value::Array(kinds)) => kinds.iter().any(|kind| kind == "Recipe"),
+        _ => false,
+    }
+}
+
+fn find_recipes<'a>(value: &'a Value, found: &mut Vec<&'a Value>) {
+    match value {
+        Value::Array(items) => {
+            for item in items {
+                find_recipes(item, found);
+            }
+        }
+        Value::Object(object) => {
+            if is_recipe(value) {
+                found.push(value);
+            } else if let Some(graph) = object.get("@graph") {
+                find_recipes(graph, found);
+            } else if let Some(list) = object.get("itemListElement") {
+                find_recipes(list, found);
+            } else if let Some(item) = object.get("item") {
+                find_recipes(item, found);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn recipe_from_value(value: &Value) -> Result<SchemaRecipe, String> {
+    let name = value
+        .get("name")
+        .and_then(Value::as_str)
+        .map(decode_entities)
+        .filter(|name| !name.is_empty())
+        .ok_or("Recipe has no name".to_string())?;
+
+    let ingredients = value
+        .get("recipeIngredient")
+        .or_else(|| value.get("ingredients"))
+        .map(text_list)
+        .unwrap_or_default();
+
+    let mut instructions = Vec::new();
+    if let Some(value) = value.get("recipeInstructions") {
+        collect_instructions(value, &mut instructions);
+    }
+
+    let recipe_yield = value.get("recipeYield").and_then(|value| match value {
+        Value::Array(items) => items.first().and_then(scalar_text),
+        value => scalar_text(value),
+    });
+
+    let author = value.get("author").and_then(|value| match value {
+        Value::Array(items) => items.first().and_then(author_name),
+        value => author_name(value),
+    });
+
+    let duration = |key: &str| {
+        value
+            .get(key)
+            .and_then(Value::as_str)
+            .and_then(parse_iso_duration)
+    };
+
+    Ok(SchemaRecipe {
+        name,
+        author,
+        ingredients,
+        instructions,
+        recipe_yield,
+        prep_time: duration("prepTime"),
+        cook_time: duration("cookTime"),
+        total_time: duration("totalTime"),
+    })
+}
+
+fn author_name(value: &Value) -> Option<String> {
+    match value {
+        Value::Object(object) => object.get("name").and_then(scalar_text),
+        value => scalar_text(value),
+    }
+}
+
+fn scalar_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(decode_entities(text)),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+    .filter(|text| !text.is_empty())
+}
+
+fn text_list(value: &Value) -> Vec<String> {
+    match value {
+        Value::Array(items) => items.iter().filter_map(scalar_text).collect(),
+        value => scalar_text(value).into_iter().collect(),
+    }
+}
+
+fn collect_instructions(value: &Value, instructions: &mut Vec<String>) {
+    match value {
+        // A single string is usually the whole method separated by newlines
+        Value::String(text) => instructions.extend(
+            decode_entities(text)
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string),
+        ),
+        Value::Array(items) => {
+            for item in items {
+                collect_instructions(item, instructions);
+            }
+        }
+        Value::Object(object) => {
+            if let Some(steps) = object.get("itemListElement") {
+                collect_instructions(steps, instructions);
+            } else if let Some(text) = object.get("text").or_else(|| object.get("name")) {
+                collect_instructions(text, instructions);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Converts an ISO 8601 duration such as `PT1H30M` into whole minutes.
+pub fn parse_iso_duration(duration: &str) -> Option<i32> {
+    let duration = duration.trim().strip_prefix('P')?;
+    let mut minutes = 0.0;
+    let mut number = String::new();
+    let mut in_time = false;
+
+    for c in duration.chars() {
+        match c {
+            'T' => in_time = true,
+            '0'..='9' | '.' => number.push(c),
+            unit => {
+                let amount: f64 = number.parse().ok()?;
+                number.clear();
+                minutes += match (unit, in_time) {
+                    ('D', false) => amount * 24.0 * 60.0,
+                    ('H', true) => amount * 60.0,
+                    ('M', true) => amount,
+                    ('S', true) => amount / 60.0,
+                    _ => return None,
+                };
+            }
+        }
+    }
+
+    Some(minutes.round() as i32)
+}
+
+/// Builds the schema.org Recipe object that `parse_recipe_document` reads back.
+pub fn recipe_to_json_ld(recipe: &SchemaRecipe) -> Value {
+    let mut value = json!({
+        "@context": "https://schema.org",
+        "@type": "Recipe",
+        "name": recipe.name,
+        "recipeIngredient": recipe.ingredients,
+        "recipeInstructions": recipe
+            .instructions
+            .iter()
+            .map(|step| json!({ "@type": "HowToStep", "text": step }))
+            .collect::<Vec<_>>(),
+    });
+
+    if let Some(author) = &recipe.author {
+        value["author"] = json!({ "@type": "Person", "name": author });
+    }
+    if let Some(recipe_yield) = &recipe.recipe_yield {
+        value["recipeYield"] = json!(recipe_yield);
+    }
+    for (key, minutes) in [
+        ("prepTime", recipe.prep_time),
+        ("cookTime", recipe.cook_time),
+        ("totalTime", recipe.total_time),
+    ] {
+        if let Some(minutes) = minutes {
+            value[key] = json!(format_iso_duration(minutes));
+        }
+    }
+
+    value
+}
+
+/// Reads the number of servings from a yield such as "4 servings" or "Serves 6-8".
+pub fn parse_servings(recipe_yield: &str) -> Option<i32> {
+    recipe_yield
+        .split(|c: char| !c.is_ascii_digit())
+        .find(|number| !number.is_empty())
+        .and_then(|number| number.parse().ok())
+        .filter(|servings| *servings > 0)
+}
+
+pub fn format_iso_duration(minutes: i32) -> String {
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("PT{}M", minutes),
+        (hours, 0) => format!("PT{}H", hours),
+        (hours, minutes) => format!("PT{}H{}M", hours, minutes),
+    }
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
@@ -0,0 +1,98 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHighlight {
+    pub field: String,
+    /// HTML escaped text with the matching words wrapped in `<mark>`
+    pub snippet: String,
+}
+
+/// Words around the first match kept in a snippet of a long text.
+const SNIPPET_CONTEXT: usize = 8;
+
+fn trigrams(word: &str) -> HashSet<String> {
+    // Padded the same way as pg_trgm so both sides agree on what is similar
+    let padded: Vec<char> = format!("  {} ", word).chars().collect();
+    padded
+        .windows(3)
+        .map(|window| window.iter().collect())
+        .collect()
+}
+
+pub fn trigram_similarity(a: &str, b: &str) -> f32 {
+    let a = trigrams(a);
+    let b = trigrams(b);
+    let shared = a.intersection(&b).count();
+    let total = a.len() + b.len() - shared;
+    if total == 0 {
+        return 0.0;
+    }
+    shared as f32 / total as f32
+}
+
+fn normalize_word(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase()
+}
+
+fn word_matches(word: &str, terms: &[String]) -> bool {
+    let word = normalize_word(word);
+    if word.is_empty() {
+        return false;
+    }
+    terms.iter().any(|term| {
+        word.contains(term.as_str())
+            || (word.chars().count() >= 3 && term.contains(word.as_str()))
+            || trigram_similarity(&word, term) >= 0.5
+    })
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Marks the words of `text` that match the search, trimming long texts to the first match.
+pub fn highlight(field: &str, text: &str, search: &str) -> Option<SearchHighlight> {
+    let terms: Vec<String> = search
+        .split_whitespace()
+        .map(normalize_word)
+        .filter(|term| !term.is_empty())
+        .collect();
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let matched: Vec<bool> = words
+        .iter()
+        .map(|word| word_matches(word, &terms))
+        .collect();
+
+    let first = matched.iter().position(|matched| *matched)?;
+    let start = first.saturating_sub(SNIPPET_CONTEXT);
+    let end = (first + SNIPPET_CONTEXT + 1).min(words.len());
+
+    let mut snippet: Vec<String> = words[start..end]
+        .iter()
+        .zip(&matched[start..end])
+        .map(|(word, matched)| {
+            if *matched {
+                format!("<mark>{}</mark>", escape_html(word))
+            } else {
+                escape_html(word)
+            }
+        })
+        .collect();
+    if start > 0 {
+        snippet.insert(0, "…".to_string());
+    }
+    if end < words.len() {
+        snippet.push("…".to_string());
+    }
+
+    Some(SearchHighlight {
+        field: field.to_string(),
+        snippet: snippet.join(" "),
+    })
+}
@@ -0,0 +1,82 @@
+use chrono::{Datelike, Utc};
+use serde::Serialize;
+
+/// Seasons stored under this region apply wherever a region has none of its own.
+pub const DEFAULT_REGION: &str = "default";
+
+/// Stores regions under one spelling so "New Zealand" and "new-zealand" are the same region.
+pub fn normalize_region(region: &str) -> String {
+    let region = region
+        .split(|c: char| c.is_whitespace() || c == '_' || c == '-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase();
+    if region.is_empty() {
+        DEFAULT_REGION.to_string()
+    } else {
+        region
+    }
+}
+
+/// Sorts and deduplicates months, which are numbered 1 for January to 12 for December.
+pub fn normalize_months(months: &[i32]) -> Result<Vec<i32>, String> {
+    if let Some(month) = months.iter().find(|month| !(1..=12).contains(*month)) {
+        return Err(format!("{} is not a month, use 1 to 12", month));
+    }
+    let mut months = months.to_vec();
+    months.sort_unstable();
+    months.dedup();
+    Ok(months)
+}
+
+#[derive(Debug, Clone)]
+pub struct SeasonIngredient {
+    pub name: String,
+    /// `None` when nobody recorded a season for the ingredient, such as salt
+    pub months: Option<Vec<i32>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SeasonScore {
+    pub month: i32,
+    /// Share of the seasonal ingredients that are in season, `None` when no ingredient has a season
+    pub score: Option<f64>,
+    pub in_season: Vec<String>,
+    pub out_of_season: Vec<String>,
+}
+
+/// Scores how in season a meal is in a month, ingredients without a season do not count either way.
+pub fn season_score(ingredients: &[SeasonIngredient], month: i32) -> SeasonScore {
+    let mut in_season = Vec::new();
+    let mut out_of_season = Vec::new();
+    for ingredient in ingredients {
+        match &ingredient.months {
+            Some(months) if months.contains(&month) => in_season.push(ingredient.name.clone()),
+            Some(_) => out_of_season.push(ingredient.name.clone()),
+            None => {}
+        }
+    }
+
+    let seasonal = in_season.len() + out_of_season.len();
+    SeasonScore {
+        month,
+        score: (seasonal > 0)
+            .then(|| (in_season.len() as f64 / seasonal as f64 * 100.0).round() / 100.0),
+        in_season,
+        out_of_season,
+    }
+}
+
+/// The regions whose seasons apply to a region, its own first.
+pub fn region_fallbacks(region: &str) -> Vec<String> {
+    let mut regions = vec![normalize_region(region)];
+    if regions[0] != DEFAULT_REGION {
+        regions.push(DEFAULT_REGION.to_string());
+    }
+    regions
+}
+
+pub fn current_month() -> i32 {
+    Utc::now().month() as i32
+}
@@ -0,0 +1,224 @@
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::units::{dimension, Dimension};
+
+#[derive(Debug, Clone)]
+pub struct IngredientAmount {
+    pub ingredient_item_id: Uuid,
+    pub name: String,
+    /// Missing when a meal lists the ingredient without saying how much
+    pub quantity: Option<f64>,
+    pub unit: Option<String>,
+}
+
+/// Quantities of one ingredient that can be added together, kept in grams or millilitres.
+struct Bucket {
+    ingredient_item_id: Uuid,
+    name: String,
+    dimension: Dimension,
+    /// The unit itself for counted units, which never convert into each other
+    count_unit: Option<String>,
+    /// The unit every entry used, if they agreed, so "2 cups + 1 cup" stays in cups
+    shared_unit: Option<Option<String>>,
+    total: f64,
+}
+
+impl Bucket {
+    fn accepts(&self, ingredient_item_id: Uuid, dimension: Dimension, unit: Option<&str>) -> bool {
+        self.ingredient_item_id == ingredient_item_id
+            && self.dimension == dimension
+            && (dimension != Dimension::Count || self.count_unit.as_deref() == unit)
+    }
+}
+
+const EMPTY_QUANTITY: f64 = 1e-6;
+
+fn round(quantity: f64) -> f64 {
+    (quantity * 100.0).round() / 100.0
+}
+
+fn display(bucket: &Bucket) -> (f64, Option<String>) {
+    if let Some(unit) = &bucket.shared_unit {
+        let (_, factor) = dimension(unit.as_deref());
+        return (round(bucket.total / factor), unit.clone());
+    }
+    // Mixed units are shown in metric, switching to kilograms and litres for large amounts
+    let (small, large) = match bucket.dimension {
+        Dimension::Mass => ("g", "kg"),
+        Dimension::Volume => ("ml", "l"),
+        Dimension::Count => return (round(bucket.total), bucket.count_unit.clone()),
+    };
+    if bucket.total >= 1000.0 {
+        (round(bucket.total / 1000.0), Some(large.to_string()))
+    } else {
+        (round(bucket.total), Some(small.to_string()))
+    }
+}
+
+/// Adds up what the planned meals need and takes away what is already in the pantry.
+pub fn build_shopping_list(
+    needed: &[IngredientAmount],
+    pantry: &[IngredientAmount],
+) -> Vec<IngredientAmount> {
+    let mut buckets: Vec<Bucket> = Vec::new();
+    let mut unmeasured: Vec<&IngredientAmount> = Vec::new();
+
+    for amount in needed {
+        let Some(quantity) = amount.quantity else {
+            if !unmeasured
+                .iter()
+                .any(|other| other.ingredient_item_id == amount.ingredient_item_id)
+            {
+                unmeasured.push(amount);
+            }
+            continue;
+        };
+        let unit = amount.unit.as_deref();
+        let (dimension, factor) = dimension(unit);
+        match buckets
+            .iter_mut()
+            .find(|bucket| bucket.accepts(amount.ingredient_item_id, dimension, unit))
+        {
+            Some(bucket) => {
+                bucket.total += quantity * factor;
+                if bucket
+                    .shared_unit
+                    .as_ref()
+                    .is_some_and(|shared| shared.as_deref() != unit)
+                {
+                    bucket.shared_unit = None;
+                }
+            }
+            None => buckets.push(Bucket {
+                ingredient_item_id: amount.ingredient_item_id,
+                name: amount.name.clone(),
+                dimension,
+                count_unit: (dimension == Dimension::Count)
+                    .then(|| amount.unit.clone())
+                    .flatten(),
+                shared_unit: Some(amount.unit.clone()),
+                total: quantity * factor,
+            }),
+        }
+    }
+
+    for item in pantry {
+        let Some(quantity) = item.quantity else {
+            continue;
+        };
+        let unit = item.unit.as_deref();
+        let (dimension, factor) = dimension(unit);
+        if let Some(bucket) = buckets
+            .iter_mut()
+            .find(|bucket| bucket.accepts(item.ingredient_item_id, dimension, unit))
+        {
+            bucket.total -= quantity * factor;
+        }
+    }
+
+    let mut list: Vec<IngredientAmount> = buckets
+        .iter()
+        .filter(|bucket| bucket.total > EMPTY_QUANTITY)
+        .map(|bucket| {
+            let (quantity, unit) = display(bucket);
+            IngredientAmount {
+                ingredient_item_id: bucket.ingredient_item_id,
+                name: bucket.name.clone(),
+                quantity: Some(quantity),
+                unit,
+            }
+        })
+        .collect();
+
+    // An ingredient without an amount is only listed when nothing else covers it
+    for amount in unmeasured {
+        let measured = buckets
+            .iter()
+            .any(|bucket| bucket.ingredient_item_id == amount.ingredient_item_id);
+        let stocked = pantry
+            .iter()
+            .any(|item| item.ingredient_item_id == amount.ingredient_item_id);
+        if !measured && !stocked {
+            list.push(amount.clone());
+        }
+    }
+
+    list.sort_by_key(|item| item.name.to_lowercase());
+    list
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShoppingListFormat {
+    Text,
+    Markdown,
+}
+
+impl ShoppingListFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ShoppingListFormat::Text => "text/plain; charset=utf-8",
+            ShoppingListFormat::Markdown => "text/markdown; charset=utf-8",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ShoppingListFormat::Text => "txt",
+            ShoppingListFormat::Markdown => "md",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ShoppingListLine {
+    pub name: String,
+    pub quantity: Option<f64>,
+    pub unit: Option<String>,
+    pub checked: bool,
+}
+
+fn line_text(line: &ShoppingListLine) -> String {
+    match (line.quantity, &line.unit) {
+        (Some(quantity), Some(unit)) => format!("{} {} {}", quantity, unit, line.name),
+        (Some(quantity), None) => format!("{} {}", quantity, line.name),
+        (None, _) => line.name.clone(),
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Writes a list grouped by section, with sections already in walking order.
+pub fn export_shopping_list(
+    format: ShoppingListFormat,
+    title: &str,
+    sections: &[(String, Vec<ShoppingListLine>)],
+) -> String {
+    let mut text = match format {
+        ShoppingListFormat::Text => format!("{}\n", title),
+        ShoppingListFormat::Markdown => format!("# {}\n", title),
+    };
+
+    for (section, lines) in sections {
+        text.push_str(&match format {
+            ShoppingListFormat::Text => format!("\n{}\n", section.to_uppercase()),
+            ShoppingListFormat::Markdown => format!("\n## {}\n\n", capitalize(section)),
+        });
+        for line in lines {
+            let mark = if line.checked { "x" } else { " " };
+            text.push_str(&match format {
+                ShoppingListFormat::Text => format!("[{}] {}\n", mark, line_text(line)),
+                ShoppingListFormat::Markdown => format!("- [{}] {}\n", mark, line_text(line)),
+            });
+        }
+    }
+
+    text
+}
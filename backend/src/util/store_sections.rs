@@ -0,0 +1,271 @@
+/// Sections in the order most supermarkets are walked, used when a store has no custom order.
+pub const DEFAULT_SECTIONS: &[&str] = &[
+    "produce",
+    "bakery",
+    "meat",
+    "seafood",
+    "dairy",
+    "frozen",
+    "pantry",
+    "spices",
+    "beverages",
+    "household",
+    "other",
+];
+
+/// Section followed by words that place an ingredient in it, checked in order.
+/// Keywords match whole words or their plural, so "water" leaves watermelon alone.
+const SECTION_KEYWORDS: &[(&str, &[&str])] = &[
+    ("frozen", &["frozen", "ice cream"]),
+    // Names that would otherwise be caught by a shorter keyword of another section
+    (
+        "produce",
+        &[
+            "eggplant",
+            "bell pepper",
+            "sweet potato",
+            "watermelon",
+            "watercress",
+        ],
+    ),
+    ("pantry", &["peanut butter", "coconut milk", "egg noodle"]),
+    (
+        "dairy",
+        &[
+            "milk",
+            "buttermilk",
+            "cheese",
+            "cheddar",
+            "mozzarella",
+            "parmesan",
+            "butter",
+            "cream",
+            "yogurt",
+            "yoghurt",
+            "egg",
+            "feta",
+            "ricotta",
+        ],
+    ),
+    (
+        "spices",
+        &[
+            "salt",
+            "black pepper",
+            "peppercorn",
+            "cumin",
+            "paprika",
+            "cinnamon",
+            "oregano",
+            "thyme",
+            "nutmeg",
+            "turmeric",
+            "chili powder",
+            "curry",
+            "spice",
+            "seasoning",
+            "vanilla",
+        ],
+    ),
+    (
+        "seafood",
+        &[
+            "fish",
+            "salmon",
+            "tuna",
+            "cod",
+            "shrimp",
+            "prawn",
+            "crab",
+            "lobster",
+            "mussel",
+            "clam",
+            "scallop",
+            "anchovy",
+            "anchovies",
+        ],
+    ),
+    (
+        "meat",
+        &[
+            "chicken", "beef", "pork", "lamb", "turkey", "bacon", "ham", "sausage", "mince",
+            "steak", "veal", "duck",
+        ],
+    ),
+    (
+        "bakery",
+        &[
+            "bread",
+            "bun",
+            "roll",
+            "bagel",
+            "baguette",
+            "tortilla",
+            "pita",
+            "croissant",
+        ],
+    ),
+    (
+        "beverages",
+        &["water", "juice", "soda", "coffee", "tea", "wine", "beer"],
+    ),
+    (
+        "produce",
+        &[
+            "apple",
+            "banana",
+            "lemon",
+            "lime",
+            "orange",
+            "berry",
+            "berries",
+            "strawberry",
+            "strawberries",
+            "blueberry",
+            "blueberries",
+            "raspberry",
+            "raspberries",
+            "cranberry",
+            "cranberries",
+            "grape",
+            "grapefruit",
+            "pineapple",
+            "melon",
+            "cress",
+            "tomato",
+            "potato",
+            "onion",
+            "garlic",
+            "shallot",
+            "carrot",
+            "celery",
+            "lettuce",
+            "spinach",
+            "kale",
+            "cabbage",
+            "broccoli",
+            "cauliflower",
+            "pepper",
+            "cucumber",
+            "zucchini",
+            "courgette",
+            "mushroom",
+            "avocado",
+            "herb",
+            "basil",
+            "parsley",
+            "cilantro",
+            "coriander",
+            "mint",
+            "ginger",
+            "leek",
+            "squash",
+            "pumpkin",
+            "pear",
+            "peach",
+            "mango",
+        ],
+    ),
+    (
+        "pantry",
+        &[
+            "flour",
+            "sugar",
+            "rice",
+            "pasta",
+            "noodle",
+            "oil",
+            "vinegar",
+            "sauce",
+            "stock",
+            "broth",
+            "bean",
+            "lentil",
+            "chickpea",
+            "oat",
+            "oatmeal",
+            "honey",
+            "syrup",
+            "nut",
+            "peanut",
+            "coconut",
+            "breadcrumb",
+            "baking",
+            "yeast",
+            "canned",
+            "tinned",
+        ],
+    ),
+];
+
+/// Normalizes a section name so "Produce " and "produce" are the same aisle.
+pub fn normalize_section(section: &str) -> String {
+    section.trim().to_lowercase()
+}
+
+/// Whether `keyword` appears in `name` as whole words, optionally followed by a plural ending.
+fn contains_word(name: &str, keyword: &str) -> bool {
+    let is_boundary = |c: Option<char>| c.is_none_or(|c| !c.is_alphabetic());
+    name.match_indices(keyword).any(|(start, _)| {
+        let rest = &name[start + keyword.len()..];
+        is_boundary(name[..start].chars().next_back())
+            && ["", "s", "es"].iter().any(|ending| {
+                rest.strip_prefix(ending)
+                    .is_some_and(|after| is_boundary(after.chars().next()))
+            })
+    })
+}
+
+/// Guesses the store section from an ingredient name when none has been set.
+pub fn guess_section(name: &str) -> &'static str {
+    let name = name.to_lowercase();
+    SECTION_KEYWORDS
+        .iter()
+        .find(|(_, keywords)| keywords.iter().any(|keyword| contains_word(&name, keyword)))
+        .map(|(section, _)| *section)
+        .unwrap_or("other")
+}
+
+/// Puts a store's own section order first, followed by any default sections it leaves out.
+pub fn section_order(custom: &[String]) -> Vec<String> {
+    let mut order: Vec<String> = custom
+        .iter()
+        .map(|section| normalize_section(section))
+        .collect();
+    for section in DEFAULT_SECTIONS {
+        if !order.iter().any(|custom| custom == section) {
+            order.push(section.to_string());
+        }
+    }
+    order
+}
+
+/// Position of a section in an order, unknown sections go last.
+pub fn section_position(order: &[String], section: &str) -> usize {
+    order
+        .iter()
+        .position(|other| other == section)
+        .unwrap_or(order.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keywords_match_whole_words() {
+        assert_eq!(guess_section("Watermelon"), "produce");
+        assert_eq!(guess_section("watercress"), "produce");
+        assert_eq!(guess_section("sparkling water"), "beverages");
+        assert_eq!(guess_section("rolled oats"), "pantry");
+        assert_eq!(guess_section("dinner rolls"), "bakery");
+    }
+
+    #[test]
+    fn keywords_match_plurals() {
+        assert_eq!(guess_section("cherry tomatoes"), "produce");
+        assert_eq!(guess_section("eggs"), "dairy");
+        assert_eq!(guess_section("frozen strawberries"), "frozen");
+        assert_eq!(guess_section("minced garlic"), "produce");
+    }
+}
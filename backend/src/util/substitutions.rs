@@ -0,0 +1,122 @@
+use super::ingredient_names::ingredient_key;
+
+/// A well known swap, `ratio` is how much of the substitute replaces one of the original.
+#[derive(Debug, Clone, Copy)]
+pub struct CommonSubstitution {
+    pub from: &'static str,
+    pub to: &'static str,
+    pub ratio: f64,
+    pub note: Option<&'static str>,
+}
+
+const fn swap(
+    from: &'static str,
+    to: &'static str,
+    ratio: f64,
+    note: Option<&'static str>,
+) -> CommonSubstitution {
+    CommonSubstitution {
+        from,
+        to,
+        ratio,
+        note,
+    }
+}
+
+/// Suggested for every library, matched by name since ingredients are created by users.
+pub const COMMON_SUBSTITUTIONS: &[CommonSubstitution] = &[
+    swap(
+        "buttermilk",
+        "milk",
+        1.0,
+        Some("stir in 1 tbsp lemon juice per cup and let it stand 5 minutes"),
+    ),
+    swap(
+        "buttermilk",
+        "plain yogurt",
+        1.0,
+        Some("thin with a little milk"),
+    ),
+    swap("butter", "margarine", 1.0, None),
+    swap("butter", "coconut oil", 0.8, Some("baking only")),
+    swap(
+        "butter",
+        "olive oil",
+        0.75,
+        Some("cooking only, not for baking"),
+    ),
+    swap("milk", "oat milk", 1.0, Some("dairy-free")),
+    swap("milk", "soy milk", 1.0, Some("dairy-free")),
+    swap("heavy cream", "coconut cream", 1.0, Some("dairy-free")),
+    swap("sour cream", "greek yogurt", 1.0, None),
+    swap(
+        "egg",
+        "flax egg",
+        1.0,
+        Some("1 tbsp ground flaxseed with 3 tbsp water, baking only"),
+    ),
+    swap("honey", "maple syrup", 1.0, Some("vegan")),
+    swap(
+        "sugar",
+        "honey",
+        0.75,
+        Some("reduce other liquids slightly"),
+    ),
+    swap("soy sauce", "tamari", 1.0, Some("gluten-free")),
+    swap("breadcrumbs", "rolled oats", 1.0, None),
+    swap(
+        "cornstarch",
+        "all-purpose flour",
+        2.0,
+        Some("for thickening"),
+    ),
+    swap(
+        "all-purpose flour",
+        "gluten-free flour blend",
+        1.0,
+        Some("gluten-free"),
+    ),
+    swap(
+        "self-raising flour",
+        "all-purpose flour",
+        1.0,
+        Some("add 1.5 tsp baking powder per cup"),
+    ),
+    swap(
+        "baking powder",
+        "baking soda",
+        0.25,
+        Some("add an acid such as lemon juice"),
+    ),
+    swap("wine", "stock", 1.0, Some("add a splash of vinegar")),
+    swap("lemon juice", "lime juice", 1.0, None),
+    swap("fresh herbs", "dried herbs", 0.33, None),
+    swap("shallot", "onion", 1.0, None),
+    swap(
+        "ground beef",
+        "lentils",
+        1.0,
+        Some("vegetarian, use cooked lentils"),
+    ),
+];
+
+/// The common swaps for an ingredient, compared the way duplicate names are.
+pub fn common_substitutions(name: &str) -> impl Iterator<Item = &'static CommonSubstitution> {
+    let key = ingredient_key(name);
+    COMMON_SUBSTITUTIONS
+        .iter()
+        .filter(move |substitution| ingredient_key(substitution.from) == key)
+}
+
+/// Describes a swap for the instructions of a variant, such as "Butter replaced by 0.8 x Coconut oil (baking only)".
+pub fn describe_substitution(from: &str, to: &str, ratio: f64, note: Option<&str>) -> String {
+    let mut description = if (ratio - 1.0).abs() < f64::EPSILON {
+        format!("{} replaced by {}", from, to)
+    } else {
+        format!("{} replaced by {} x {}", from, ratio, to)
+    };
+    if let Some(note) = note.filter(|note| !note.trim().is_empty()) {
+        description.push_str(&format!(" ({})", note.trim()));
+    }
+    description
+}
@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+
+use super::terms::{normalize_term, sorted_terms};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Cuisine {
+    American,
+    British,
+    Caribbean,
+    Chinese,
+    French,
+    German,
+    Greek,
+    Indian,
+    Italian,
+    Japanese,
+    Korean,
+    Mediterranean,
+    Mexican,
+    MiddleEastern,
+    Spanish,
+    Thai,
+    Vietnamese,
+}
+
+/// Every cuisine in the order they are offered.
+pub const CUISINES: &[Cuisine] = &[
+    Cuisine::American,
+    Cuisine::British,
+    Cuisine::Caribbean,
+    Cuisine::Chinese,
+    Cuisine::French,
+    Cuisine::German,
+    Cuisine::Greek,
+    Cuisine::Indian,
+    Cuisine::Italian,
+    Cuisine::Japanese,
+    Cuisine::Korean,
+    Cuisine::Mediterranean,
+    Cuisine::Mexican,
+    Cuisine::MiddleEastern,
+    Cuisine::Spanish,
+    Cuisine::Thai,
+    Cuisine::Vietnamese,
+];
+
+impl Cuisine {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Cuisine::American => "american",
+            Cuisine::British => "british",
+            Cuisine::Caribbean => "caribbean",
+            Cuisine::Chinese => "chinese",
+            Cuisine::French => "french",
+            Cuisine::German => "german",
+            Cuisine::Greek => "greek",
+            Cuisine::Indian => "indian",
+            Cuisine::Italian => "italian",
+            Cuisine::Japanese => "japanese",
+            Cuisine::Korean => "korean",
+            Cuisine::Mediterranean => "mediterranean",
+            Cuisine::Mexican => "mexican",
+            Cuisine::MiddleEastern => "middle_eastern",
+            Cuisine::Spanish => "spanish",
+            Cuisine::Thai => "thai",
+            Cuisine::Vietnamese => "vietnamese",
+        }
+    }
+
+    pub fn parse(cuisine: &str) -> Option<Self> {
+        CUISINES
+            .iter()
+            .copied()
+            .find(|candidate| candidate.as_str() == cuisine)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Course {
+    Breakfast,
+    Appetizer,
+    Soup,
+    Salad,
+    Main,
+    Side,
+    Dessert,
+    Snack,
+    Drink,
+}
+
+/// Every course roughly in the order they are eaten.
+pub const COURSES: &[Course] = &[
+    Course::Breakfast,
+    Course::Appetizer,
+    Course::Soup,
+    Course::Salad,
+    Course::Main,
+    Course::Side,
+    Course::Dessert,
+    Course::Snack,
+    Course::Drink,
+];
+
+impl Course {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Course::Breakfast => "breakfast",
+            Course::Appetizer => "appetizer",
+            Course::Soup => "soup",
+            Course::Salad => "salad",
+            Course::Main => "main",
+            Course::Side => "side",
+            Course::Dessert => "dessert",
+            Course::Snack => "snack",
+            Course::Drink => "drink",
+        }
+    }
+
+    pub fn parse(course: &str) -> Option<Self> {
+        COURSES
+            .iter()
+            .copied()
+            .find(|candidate| candidate.as_str() == course)
+    }
+}
+
+/// Tags are free-form, so " #Weeknight " and "weeknight" only need to end up the same.
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = normalize_term(tag.trim().trim_start_matches('#'), &[])?;
+    Some(tag.chars().take(64).collect())
+}
+
+/// Normalizes a list of tags, sorted and without duplicates.
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    sorted_terms(tags.iter().filter_map(|tag| normalize_tag(tag)).collect())
+}
@@ -0,0 +1,44 @@
+/// Lowercases a free-form term and collapses its whitespace, mapping a synonym to its usual name.
+pub fn normalize_term(term: &str, synonyms: &[(&str, &str)]) -> Option<String> {
+    let term = term
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    if term.is_empty() {
+        return None;
+    }
+    Some(
+        synonyms
+            .iter()
+            .find(|(synonym, _)| *synonym == term)
+            .map(|(_, name)| name.to_string())
+            .unwrap_or(term),
+    )
+}
+
+/// Normalizes a list of terms, sorted and without duplicates.
+pub fn normalize_terms(terms: &[String], synonyms: &[(&str, &str)]) -> Vec<String> {
+    sorted_terms(
+        terms
+            .iter()
+            .filter_map(|term| normalize_term(term, synonyms))
+            .collect(),
+    )
+}
+
+/// Sorts terms and drops duplicates.
+pub fn sorted_terms(mut terms: Vec<String>) -> Vec<String> {
+    terms.sort();
+    terms.dedup();
+    terms
+}
+
+/// The known terms followed by the custom ones in use, each flagged with whether it is known.
+pub fn known_and_custom(known: &[&str], custom: Vec<String>) -> Vec<(String, bool)> {
+    known
+        .iter()
+        .map(|term| (term.to_string(), true))
+        .chain(custom.into_iter().map(|term| (term, false)))
+        .collect()
+}
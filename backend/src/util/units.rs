@@ -0,0 +1,61 @@
+use super::ingredient_parser::canonical_unit;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dimension {
+    Mass,
+    Volume,
+    Count,
+}
+
+/// Size of each unit in grams or millilitres, anything else is counted as is.
+const FACTORS: &[(&str, Dimension, f64)] = &[
+    ("mg", Dimension::Mass, 0.001),
+    ("g", Dimension::Mass, 1.0),
+    ("kg", Dimension::Mass, 1000.0),
+    ("oz", Dimension::Mass, 28.349_523),
+    ("lb", Dimension::Mass, 453.592_37),
+    ("ml", Dimension::Volume, 1.0),
+    ("cl", Dimension::Volume, 10.0),
+    ("dl", Dimension::Volume, 100.0),
+    ("l", Dimension::Volume, 1000.0),
+    ("tsp", Dimension::Volume, 4.928_922),
+    ("tbsp", Dimension::Volume, 14.786_765),
+    ("fl oz", Dimension::Volume, 29.573_53),
+    ("cup", Dimension::Volume, 236.588_24),
+    ("pt", Dimension::Volume, 473.176_47),
+    ("qt", Dimension::Volume, 946.352_95),
+    ("gal", Dimension::Volume, 3_785.411_8),
+];
+
+/// Stores units under one spelling so "Tbsp." and "tablespoons" compare equal.
+pub fn normalize_unit(unit: Option<&str>) -> Option<String> {
+    let unit = unit?.trim();
+    if unit.is_empty() {
+        return None;
+    }
+    Some(
+        canonical_unit(unit)
+            .map(str::to_string)
+            .unwrap_or_else(|| unit.to_lowercase()),
+    )
+}
+
+pub fn dimension(unit: Option<&str>) -> (Dimension, f64) {
+    unit.and_then(|unit| FACTORS.iter().find(|(name, _, _)| *name == unit))
+        .map(|(_, dimension, factor)| (*dimension, *factor))
+        .unwrap_or((Dimension::Count, 1.0))
+}
+
+/// Converts between two normalized units, `None` when they measure different things.
+pub fn convert(quantity: f64, from: Option<&str>, to: Option<&str>) -> Option<f64> {
+    if from == to {
+        return Some(quantity);
+    }
+    let (from_dimension, from_factor) = dimension(from);
+    let (to_dimension, to_factor) = dimension(to);
+    // Counted units such as "clove" only convert to themselves
+    if from_dimension != to_dimension || from_dimension == Dimension::Count {
+        return None;
+    }
+    Some(quantity * from_factor / to_factor)
+}
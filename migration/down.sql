@@ -1,6 +1,29 @@
+DROP TABLE IF EXISTS import_job_items;
+DROP TABLE IF EXISTS import_jobs;
+DROP TABLE IF EXISTS ingredient_prices;
+DROP TABLE IF EXISTS store_sections;
+DROP TABLE IF EXISTS stores;
+DROP TABLE IF EXISTS shopping_list_items;
+DROP TABLE IF EXISTS shopping_lists;
+DROP TABLE IF EXISTS pantry_items;
+DROP TABLE IF EXISTS user_regions;
+DROP TABLE IF EXISTS user_equipment;
+DROP TABLE IF EXISTS user_allergies;
 DROP TABLE IF EXISTS calendar_items;
+DROP TABLE IF EXISTS meal_item_diet_overrides;
+DROP FUNCTION IF EXISTS expand_meal_item;
+DROP TABLE IF EXISTS meal_item_components;
+DROP TABLE IF EXISTS meal_item_amounts;
+DROP TABLE IF EXISTS ingredient_seasons;
+DROP TABLE IF EXISTS ingredient_substitutions;
+DROP TABLE IF EXISTS ingredient_aliases;
 DROP TABLE IF EXISTS ingredient_items;
+DROP TABLE IF EXISTS reference_foods;
+DROP VIEW IF EXISTS ingredient_category_ancestors;
+DROP TABLE IF EXISTS ingredient_categories;
 DROP TABLE IF EXISTS meal_items;
+DROP FUNCTION IF EXISTS restrict_ingredient_item_delete;
+DROP FUNCTION IF EXISTS check_meal_ingredient_items;
 DROP TABLE IF EXISTS password_reset_codes;
 DROP TABLE IF EXISTS email_verify_codes;
 DROP TABLE IF EXISTS sessions;
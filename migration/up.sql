@@ -1,3 +1,4 @@
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
 CREATE TABLE users (
     id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
     first_name varchar(255) NOT NULL,
@@ -39,26 +40,291 @@ CREATE TABLE password_reset_codes (
     expire_date TIMESTAMPTZ NOT NULL,
     creation_date TIMESTAMPTZ NOT NULL DEFAULT NOW()
 );
+CREATE TABLE ingredient_categories (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    name varchar(255) NOT NULL,
+    parent_id uuid REFERENCES ingredient_categories(id) ON DELETE SET NULL,
+    store_section varchar(255),
+    creator_id uuid NOT NULL REFERENCES users(id),
+    creation_date TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE UNIQUE INDEX ingredient_categories_name_idx ON ingredient_categories (lower(name));
+CREATE VIEW ingredient_category_ancestors AS
+WITH RECURSIVE tree (category_id, ancestor_id, depth) AS (
+    SELECT id, id, 0 FROM ingredient_categories
+    UNION ALL
+    SELECT t.category_id, c.parent_id, t.depth + 1
+    FROM tree t
+    JOIN ingredient_categories c ON c.id = t.ancestor_id
+    WHERE c.parent_id IS NOT NULL AND t.depth < 32
+)
+SELECT category_id, ancestor_id, depth FROM tree;
+CREATE TABLE reference_foods (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    source varchar(32) NOT NULL,
+    source_id varchar(255) NOT NULL,
+    name varchar(255) NOT NULL,
+    brand varchar(255),
+    barcode varchar(14),
+    calories float8,
+    protein float8,
+    fat float8,
+    carbohydrates float8,
+    fiber float8,
+    sugar float8,
+    sodium float8,
+    import_date TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (source, source_id)
+);
+CREATE INDEX reference_foods_name_trgm_idx ON reference_foods USING GIN (lower(name) gin_trgm_ops);
+CREATE INDEX reference_foods_barcode_idx ON reference_foods (barcode);
 CREATE TABLE ingredient_items (
     id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
     name varchar(255) NOT NULL,
+    name_key varchar(255) NOT NULL,
     creator_id uuid NOT NULL REFERENCES users(id),
+    category_id uuid REFERENCES ingredient_categories(id) ON DELETE SET NULL,
+    store_section varchar(255),
+    calories float8,
+    protein float8,
+    fat float8,
+    carbohydrates float8,
+    fiber float8,
+    sugar float8,
+    sodium float8,
+    density float8,
+    piece_weight float8,
+    reference_food_id uuid REFERENCES reference_foods(id) ON DELETE SET NULL,
+    barcode varchar(14) UNIQUE,
+    allergens varchar(64) [] NOT NULL DEFAULT '{}',
+    properties varchar(32) [],
     creation_date TIMESTAMPTZ NOT NULL DEFAULT NOW()
 );
+CREATE INDEX ingredient_items_name_trgm_idx ON ingredient_items USING GIN (lower(name) gin_trgm_ops);
+CREATE INDEX ingredient_items_name_key_idx ON ingredient_items (name_key);
+CREATE INDEX ingredient_items_allergens_idx ON ingredient_items USING GIN (allergens);
+CREATE TABLE ingredient_aliases (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    ingredient_item_id uuid NOT NULL REFERENCES ingredient_items(id) ON DELETE CASCADE,
+    name varchar(255) NOT NULL,
+    name_key varchar(255) NOT NULL UNIQUE,
+    creator_id uuid NOT NULL REFERENCES users(id),
+    creation_date TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX ingredient_aliases_name_trgm_idx ON ingredient_aliases USING GIN (lower(name) gin_trgm_ops);
+CREATE TABLE ingredient_substitutions (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    ingredient_item_id uuid NOT NULL REFERENCES ingredient_items(id) ON DELETE CASCADE,
+    substitute_id uuid NOT NULL REFERENCES ingredient_items(id) ON DELETE CASCADE,
+    ratio float8 NOT NULL DEFAULT 1 CHECK (ratio > 0),
+    note text,
+    creator_id uuid NOT NULL REFERENCES users(id),
+    creation_date TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (ingredient_item_id, substitute_id),
+    CHECK (ingredient_item_id <> substitute_id)
+);
+CREATE TABLE ingredient_seasons (
+    ingredient_item_id uuid NOT NULL REFERENCES ingredient_items(id) ON DELETE CASCADE,
+    region varchar(64) NOT NULL,
+    months int [] NOT NULL,
+    PRIMARY KEY (ingredient_item_id, region)
+);
 CREATE TABLE meal_items (
     id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
     name varchar(255) NOT NULL,
     ingredient_items uuid [] NOT NULL,
     instructions text NOT NULL,
+    servings int NOT NULL DEFAULT 1 CHECK (servings > 0),
+    prep_time int CHECK (prep_time >= 0),
+    cook_time int CHECK (cook_time >= 0),
+    total_time int CHECK (total_time >= 0),
+    -- The given total time, otherwise whatever prep and cook time are known
+    ready_time int GENERATED ALWAYS AS (COALESCE(total_time, prep_time + cook_time, prep_time, cook_time)) STORED,
+    difficulty varchar(16) CHECK (difficulty IN ('easy', 'medium', 'hard')),
+    equipment varchar(64) [] NOT NULL DEFAULT '{}',
+    cuisine varchar(32),
+    course varchar(32),
+    tags varchar(64) [] NOT NULL DEFAULT '{}',
+    diets varchar(32) [] NOT NULL DEFAULT '{}',
+    -- Every ingredient and piece of equipment of the meal and its sub-recipes, and their
+    -- ready times added up, kept up to date with diets
+    expanded_ingredient_items uuid [] NOT NULL DEFAULT '{}',
+    expanded_equipment varchar(64) [] NOT NULL DEFAULT '{}',
+    expanded_ready_time int,
     creator_id uuid NOT NULL REFERENCES users(id),
+    forked_from uuid REFERENCES meal_items(id) ON DELETE SET NULL,
+    forked_from_creator_id uuid REFERENCES users(id),
+    fork_sync_date TIMESTAMPTZ,
+    update_date TIMESTAMPTZ NOT NULL DEFAULT NOW(),
     creation_date TIMESTAMPTZ NOT NULL DEFAULT NOW()
 );
+CREATE INDEX meal_items_name_trgm_idx ON meal_items USING GIN (lower(name) gin_trgm_ops);
+CREATE INDEX meal_items_instructions_fts_idx ON meal_items USING GIN (to_tsvector('english', instructions));
+CREATE INDEX meal_items_ingredient_items_idx ON meal_items USING GIN (ingredient_items);
+CREATE INDEX meal_items_expanded_ingredient_items_idx ON meal_items USING GIN (expanded_ingredient_items);
+CREATE INDEX meal_items_diets_idx ON meal_items USING GIN (diets);
+CREATE INDEX meal_items_expanded_equipment_idx ON meal_items USING GIN (expanded_equipment);
+CREATE INDEX meal_items_tags_idx ON meal_items USING GIN (tags);
+CREATE FUNCTION check_meal_ingredient_items() RETURNS trigger AS $$
+BEGIN
+    -- Locks the referenced rows the same way a foreign key would
+    IF (
+        SELECT count(*) FROM (
+            SELECT id FROM ingredient_items WHERE id = ANY(NEW.ingredient_items) FOR KEY SHARE
+        ) f
+    ) < (SELECT count(DISTINCT u) FROM unnest(NEW.ingredient_items) u) THEN
+        RAISE EXCEPTION 'meal_items.ingredient_items references a missing ingredient item'
+            USING ERRCODE = 'foreign_key_violation';
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+CREATE TRIGGER meal_items_ingredient_items_fk BEFORE INSERT OR UPDATE OF ingredient_items ON meal_items
+    FOR EACH ROW EXECUTE FUNCTION check_meal_ingredient_items();
+CREATE FUNCTION restrict_ingredient_item_delete() RETURNS trigger AS $$
+BEGIN
+    IF EXISTS (SELECT 1 FROM meal_items WHERE ingredient_items @> ARRAY[OLD.id]) THEN
+        RAISE EXCEPTION 'ingredient item % is still used by meal items', OLD.id
+            USING ERRCODE = 'foreign_key_violation';
+    END IF;
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+CREATE TRIGGER ingredient_items_meal_items_fk BEFORE DELETE ON ingredient_items
+    FOR EACH ROW EXECUTE FUNCTION restrict_ingredient_item_delete();
+CREATE TABLE meal_item_amounts (
+    meal_item_id uuid NOT NULL REFERENCES meal_items(id) ON DELETE CASCADE,
+    ingredient_item_id uuid NOT NULL REFERENCES ingredient_items(id) ON DELETE CASCADE,
+    quantity float8 NOT NULL,
+    unit varchar(255),
+    PRIMARY KEY (meal_item_id, ingredient_item_id)
+);
+CREATE TABLE meal_item_components (
+    meal_item_id uuid NOT NULL REFERENCES meal_items(id) ON DELETE CASCADE,
+    component_id uuid NOT NULL REFERENCES meal_items(id) ON DELETE RESTRICT,
+    -- Servings of the component, so half of a dough that makes 2 pizzas is 1
+    quantity float8 NOT NULL DEFAULT 1 CHECK (quantity > 0),
+    position int NOT NULL,
+    PRIMARY KEY (meal_item_id, component_id),
+    CHECK (meal_item_id <> component_id)
+);
+CREATE INDEX meal_item_components_component_id_idx ON meal_item_components (component_id);
+CREATE FUNCTION expand_meal_item(root uuid) RETURNS TABLE (meal_item_id uuid, scale float8) AS $$
+    -- The meal itself and every nested component, scaled to how much of it the meal uses.
+    -- Saving rejects cycles, the depth limit only guards against one slipping through.
+    WITH RECURSIVE expanded (meal_item_id, scale, depth) AS (
+        SELECT root, 1::float8, 0
+        UNION ALL
+        SELECT c.component_id, e.scale * c.quantity / m.servings, e.depth + 1
+        FROM expanded e
+        JOIN meal_item_components c ON c.meal_item_id = e.meal_item_id
+        JOIN meal_items m ON m.id = c.component_id
+        WHERE e.depth < 16
+    )
+    SELECT meal_item_id, scale FROM expanded
+$$ LANGUAGE sql STABLE;
+CREATE TABLE meal_item_diet_overrides (
+    meal_item_id uuid NOT NULL REFERENCES meal_items(id) ON DELETE CASCADE,
+    diet varchar(32) NOT NULL,
+    applies boolean NOT NULL,
+    note text,
+    PRIMARY KEY (meal_item_id, diet)
+);
 CREATE TABLE calendar_items (
     id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
     user_id uuid NOT NULL REFERENCES users(id),
     shared_with uuid [] NOT NULL,
-    meal_item_id uuid REFERENCES meal_items(id),
+    meal_item_id uuid REFERENCES meal_items(id) ON DELETE RESTRICT,
+    start_date TIMESTAMPTZ NOT NULL,
+    end_date TIMESTAMPTZ NOT NULL,
+    cooked_date TIMESTAMPTZ,
+    creation_date TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE TABLE import_jobs (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id uuid NOT NULL REFERENCES users(id),
+    format varchar(255) NOT NULL,
+    status varchar(255) NOT NULL DEFAULT 'running',
+    error text,
+    finish_date TIMESTAMPTZ,
+    creation_date TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE TABLE import_job_items (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    job_id uuid NOT NULL REFERENCES import_jobs(id) ON DELETE CASCADE,
+    name varchar(255) NOT NULL,
+    status varchar(255) NOT NULL,
+    meal_item_id uuid REFERENCES meal_items(id) ON DELETE SET NULL,
+    message text,
+    creation_date TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE TABLE user_allergies (
+    user_id uuid NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    allergen varchar(64) NOT NULL,
+    PRIMARY KEY (user_id, allergen)
+);
+CREATE TABLE user_equipment (
+    user_id uuid NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    equipment varchar(64) NOT NULL,
+    PRIMARY KEY (user_id, equipment)
+);
+CREATE TABLE user_regions (
+    user_id uuid PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    region varchar(64) NOT NULL
+);
+CREATE TABLE pantry_items (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id uuid NOT NULL REFERENCES users(id),
+    ingredient_item_id uuid NOT NULL REFERENCES ingredient_items(id) ON DELETE CASCADE,
+    quantity float8 NOT NULL,
+    unit varchar(255),
+    location varchar(255) NOT NULL CHECK (location IN ('fridge', 'freezer', 'cupboard')),
+    best_before DATE,
+    barcode varchar(14),
+    creation_date TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX pantry_items_user_id_idx ON pantry_items (user_id, best_before);
+CREATE TABLE shopping_lists (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id uuid NOT NULL REFERENCES users(id),
+    shared_with uuid [] NOT NULL DEFAULT '{}',
+    name varchar(255) NOT NULL,
     start_date TIMESTAMPTZ NOT NULL,
     end_date TIMESTAMPTZ NOT NULL,
     creation_date TIMESTAMPTZ NOT NULL DEFAULT NOW()
-);
\ No newline at end of file
+);
+CREATE TABLE shopping_list_items (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    shopping_list_id uuid NOT NULL REFERENCES shopping_lists(id) ON DELETE CASCADE,
+    ingredient_item_id uuid REFERENCES ingredient_items(id) ON DELETE SET NULL,
+    name varchar(255) NOT NULL,
+    quantity float8,
+    unit varchar(255),
+    checked BOOLEAN NOT NULL DEFAULT FALSE,
+    checked_by uuid REFERENCES users(id),
+    creation_date TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE TABLE stores (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id uuid NOT NULL REFERENCES users(id),
+    name varchar(255) NOT NULL,
+    creation_date TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE TABLE store_sections (
+    store_id uuid NOT NULL REFERENCES stores(id) ON DELETE CASCADE,
+    section varchar(255) NOT NULL,
+    position int NOT NULL,
+    PRIMARY KEY (store_id, section)
+);
+CREATE TABLE ingredient_prices (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    user_id uuid NOT NULL REFERENCES users(id),
+    ingredient_item_id uuid NOT NULL REFERENCES ingredient_items(id) ON DELETE CASCADE,
+    store_id uuid REFERENCES stores(id) ON DELETE SET NULL,
+    price float8 NOT NULL CHECK (price >= 0),
+    quantity float8 NOT NULL DEFAULT 1 CHECK (quantity > 0),
+    unit varchar(255),
+    price_date DATE NOT NULL DEFAULT CURRENT_DATE,
+    creation_date TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+CREATE INDEX ingredient_prices_user_id_idx ON ingredient_prices (user_id, ingredient_item_id, price_date);
\ No newline at end of file